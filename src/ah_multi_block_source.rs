@@ -1,11 +1,16 @@
 // src/ah_multi_block_source.rs
 
 use anyhow::{Context, Result, anyhow};
+use indicatif::ProgressBar;
+use std::time::Duration;
 use subxt::utils::H256;
 use subxt::{OnlineClient, config::PolkadotConfig};
+use tokio::time::timeout;
 
 use crate::asset_hub;
-use crate::types::{AccountId, Balance, ElectionSnapshot, Hash, VoteWeight, VoterSnapshot};
+use crate::types::{
+    AccountId, Balance, ElectionSnapshot, Hash, SNAPSHOT_SCHEMA_VERSION, VoteWeight, VoterSnapshot,
+};
 
 use subxt::config::substrate::AccountId32;
 
@@ -14,6 +19,78 @@ fn account32_to_local(acc: AccountId32) -> AccountId {
     *acc.as_ref()
 }
 
+/// Report a snapshot page fetch that did not finish within `page_timeout`.
+fn warn_page_timeout(kind: &str, page_idx: u32, page_timeout: Duration) {
+    eprintln!(
+        "[warn] {} page {} not fetched within {:?}",
+        kind, page_idx, page_timeout
+    );
+}
+
+/// Advance `progress` by one fetched snapshot page.
+fn report_page(progress: &ProgressBar, kind: &str, page_idx: u32, found: bool) {
+    if !progress.is_hidden() {
+        progress.set_message(format!(
+            "{} page {}: {}",
+            kind,
+            page_idx,
+            if found { "found" } else { "empty" }
+        ));
+    }
+    progress.inc(1);
+}
+
+use crate::asset_hub::api::runtime_types::pallet_election_provider_multi_block::types::Phase;
+
+/// Whether a complete snapshot can be read at a block, and if not, why.
+#[derive(Debug)]
+pub enum SnapshotAvailability {
+    /// Snapshot for `round` is present and the phase keeps it stable.
+    Available { round: u32 },
+    /// `Round` or `CurrentPhase` is not set; the pallet has not run yet.
+    PalletUninitialized,
+    /// The phase does not guarantee a complete snapshot (e.g. `Off`, `Snapshot(_)`).
+    WrongPhase { phase: Phase },
+    /// The phase allows a snapshot but no target page exists for the round.
+    EmptySnapshot,
+}
+
+/// Phases in which the snapshot is complete and stable.
+fn phase_allows_snapshot(phase: &Phase) -> bool {
+    matches!(
+        phase,
+        Phase::Emergency
+            | Phase::Signed(_)
+            | Phase::SignedValidation(_)
+            | Phase::Unsigned(_)
+            | Phase::Export(_)
+            | Phase::Done
+    )
+}
+
+/// Storage reads pinned to a single AssetHub block.
+type StorageAt = subxt::storage::Storage<PolkadotConfig, OnlineClient<PolkadotConfig>>;
+
+/// Read `CurrentPhase`; error if it is not set.
+async fn read_phase(storage: &StorageAt) -> Result<Phase> {
+    let phase_addr = asset_hub::api::storage()
+        .multi_block_election()
+        .current_phase();
+    storage
+        .fetch(&phase_addr)
+        .await?
+        .context("CurrentPhase storage returned None at this block")
+}
+
+/// Read the multi-block election `Round`; error if it is not set.
+async fn read_round(storage: &StorageAt) -> Result<u32> {
+    let round_addr = asset_hub::api::storage().multi_block_election().round();
+    storage
+        .fetch(&round_addr)
+        .await?
+        .context("Round storage returned None at this block")
+}
+
 /// Using `pallet-election-provider-multi-block` on AssetHub.
 pub struct AhMultiBlockSource {
     client: OnlineClient<PolkadotConfig>,
@@ -28,41 +105,51 @@ impl AhMultiBlockSource {
         Ok(Self { client })
     }
 
-    /// Ensure that the election phase is one where the snapshot is complete and stable.
+    /// Ensure that the election phase is one where the snapshot is complete and stable,
+    /// and return that phase.
     ///
     /// Allowed phases:
     ///   Emergency | Signed(_) | SignedValidation(_) | Unsigned(_) | Export(_) | Done
-    async fn ensure_phase_allows_snapshot(&self, at_hash: H256) -> Result<()> {
-        use crate::asset_hub::api::runtime_types::pallet_election_provider_multi_block::types::Phase;
+    async fn ensure_phase_allows_snapshot(storage: &StorageAt) -> Result<Phase> {
+        let phase = read_phase(storage).await?;
 
-        let storage = self.client.storage().at(at_hash);
-        let root_storage = asset_hub::api::storage();
-        let epmb = root_storage.multi_block_election();
-
-        let phase_addr = epmb.current_phase();
-        let phase: Phase = storage
-            .fetch(&phase_addr)
-            .await?
-            .context("CurrentPhase storage returned None at this block")?;
-
-        let allowed = matches!(
-            phase,
-            Phase::Emergency
-                | Phase::Signed(_)
-                | Phase::SignedValidation(_)
-                | Phase::Unsigned(_)
-                | Phase::Export(_)
-                | Phase::Done
-        );
-
-        if !allowed {
+        if !phase_allows_snapshot(&phase) {
             return Err(anyhow!(
                 "snapshot is not guaranteed to be complete in current phase: {:?}",
                 phase
             ));
         }
 
-        Ok(())
+        Ok(phase)
+    }
+
+    /// Classify whether `snapshot_at` would succeed at `at`, without fetching pages.
+    ///
+    /// Unlike `snapshot_at`, a missing `Round` or a wrong phase is not an error here;
+    /// only RPC / decoding failures are.
+    pub async fn check_snapshot_availability(&self, at: Hash) -> Result<SnapshotAvailability> {
+        let storage = self.client.storage().at(H256::from(at));
+        let epmb = asset_hub::api::storage().multi_block_election();
+
+        let Some(round) = storage.fetch(&epmb.round()).await? else {
+            return Ok(SnapshotAvailability::PalletUninitialized);
+        };
+        let Some(phase) = storage.fetch(&epmb.current_phase()).await? else {
+            return Ok(SnapshotAvailability::PalletUninitialized);
+        };
+        if !phase_allows_snapshot(&phase) {
+            return Ok(SnapshotAvailability::WrongPhase { phase });
+        }
+
+        // At most one target page exists per round; any key means a snapshot.
+        let mut target_pages = storage
+            .iter(epmb.paged_target_snapshot_iter1(round))
+            .await?;
+        if target_pages.next().await.transpose()?.is_none() {
+            return Ok(SnapshotAvailability::EmptySnapshot);
+        }
+
+        Ok(SnapshotAvailability::Available { round })
     }
 
     /// Build an `ElectionSnapshot` from pallet-election-provider-multi-block
@@ -70,24 +157,33 @@ impl AhMultiBlockSource {
     ///
     /// - `at` is the `[u8; 32]` block hash.
     /// - `max_pages` is the runtime `MultiBlockElection::Pages` value or a safe upper bound.
-    pub async fn snapshot_at(&self, at: Hash, max_pages: u32) -> Result<ElectionSnapshot> {
-        let at_hash = H256::from(at);
-
-        // Ensure a stable snapshot phase.
-        self.ensure_phase_allows_snapshot(at_hash).await?;
-
-        let storage = self.client.storage().at(at_hash);
+    /// - `progress` is advanced once per fetched target and voter page.
+    /// - `page_timeout` bounds each page fetch; the remaining pages are still fetched
+    ///   after a timeout, but the snapshot is then incomplete and an error is returned.
+    ///
+    /// Every read goes through one storage handle pinned to `at`, so all pages come
+    /// from the same block state. Afterwards `Round` and `CurrentPhase` are read again
+    /// at the chain head, and any phase transition or new round since `at` is logged
+    /// as a warning: the snapshot then describes an election that has moved on.
+    pub async fn snapshot_at(
+        &self,
+        at: Hash,
+        max_pages: u32,
+        progress: &ProgressBar,
+        page_timeout: Duration,
+    ) -> Result<ElectionSnapshot> {
+        // The only storage handle used below; every read is at `at`.
+        let storage = self.client.storage().at(H256::from(at));
         let root_storage = asset_hub::api::storage();
 
         let epmb = root_storage.multi_block_election();
         let balances = root_storage.balances();
 
+        // Ensure a stable snapshot phase.
+        let phase = Self::ensure_phase_allows_snapshot(&storage).await?;
+
         // Read the current round: Round<T> = u32.
-        let round_addr = epmb.round();
-        let round: u32 = storage
-            .fetch(&round_addr)
-            .await?
-            .context("Round storage returned None at this block")?;
+        let round = read_round(&storage).await?;
 
         // Rebuild `all_targets` from paged target snapshots.
         //
@@ -98,12 +194,21 @@ impl AhMultiBlockSource {
         // Scan [0..max_pages) and concatenate all pages in order, mirroring
         // how the internal snapshot helper flattens targets.
         use std::collections::BTreeSet;
+        let mut timed_out_pages = 0u32;
         let mut target_set: BTreeSet<AccountId> = BTreeSet::new();
         let mut all_targets: Vec<AccountId> = Vec::new();
 
         for page_idx in 0..max_pages {
             let t_addr = epmb.paged_target_snapshot(round, page_idx);
-            let page_opt = storage.fetch(&t_addr).await?;
+            let page_opt = match timeout(page_timeout, storage.fetch(&t_addr)).await {
+                Ok(page) => page?,
+                Err(_) => {
+                    warn_page_timeout("target", page_idx, page_timeout);
+                    timed_out_pages += 1;
+                    None
+                }
+            };
+            report_page(progress, "target", page_idx, page_opt.is_some());
 
             let Some(targets_page) = page_opt else {
                 continue;
@@ -129,7 +234,15 @@ impl AhMultiBlockSource {
 
         for page_idx in 0..max_pages {
             let v_addr = epmb.paged_voter_snapshot(round, page_idx);
-            let page_opt = storage.fetch(&v_addr).await?;
+            let page_opt = match timeout(page_timeout, storage.fetch(&v_addr)).await {
+                Ok(page) => page?,
+                Err(_) => {
+                    warn_page_timeout("voter", page_idx, page_timeout);
+                    timed_out_pages += 1;
+                    None
+                }
+            };
+            report_page(progress, "voter", page_idx, page_opt.is_some());
 
             let mut this_page: Vec<VoterSnapshot> = Vec::new();
 
@@ -151,22 +264,62 @@ impl AhMultiBlockSource {
             voter_pages.push(this_page);
         }
 
+        if timed_out_pages > 0 {
+            return Err(anyhow!(
+                "{} snapshot page(s) timed out after {:?} at block 0x{}; \
+                 the snapshot would be incomplete",
+                timed_out_pages,
+                page_timeout,
+                hex::encode(at)
+            ));
+        }
+
         // Total issuance at that block (Balances::TotalIssuance).
         let total_issuance_addr = balances.total_issuance();
         let total_issuance: Balance = storage.fetch(&total_issuance_addr).await?.unwrap_or(0);
 
         // DesiredTargets(round) = desired validator count for this round.
-        let desired_addr = epmb.desired_targets(round);
-        let desired: Option<u32> = storage.fetch(&desired_addr).await?;
-        let desired_targets = desired.unwrap_or(all_targets.len() as u32);
+        // If unset, fall back to electing every target (and say so); use
+        // `run-offline --desired-source` to pick a different source later.
+        let desired_targets = match storage.fetch(&epmb.desired_targets(round)).await? {
+            Some(desired) => desired,
+            None => {
+                eprintln!(
+                    "[warn] DesiredTargets({}) not set; using all_targets.len()={} as desired_targets",
+                    round,
+                    all_targets.len()
+                );
+                all_targets.len() as u32
+            }
+        };
+
+        // The pages above are consistent with each other, but the chain may have moved
+        // on since `at`; compare with a fresh read at the head.
+        let head = self.client.storage().at_latest().await?;
+        let phase_head = read_phase(&head).await?;
+        let round_head = read_round(&head).await?;
+        // `Phase` has no `PartialEq`; its `Debug` form covers every field.
+        if format!("{:?}", phase_head) != format!("{:?}", phase) || round_head != round {
+            eprintln!(
+                "[warn] election state changed between snapshot block 0x{} and the chain head: \
+                 phase {:?} -> {:?}, round {} -> {}",
+                hex::encode(at),
+                phase,
+                phase_head,
+                round,
+                round_head
+            );
+        }
 
         Ok(ElectionSnapshot {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
             at,
             round,
             total_issuance,
             desired_targets,
             all_targets,
             voter_pages,
+            checksum: None,
         })
     }
 }
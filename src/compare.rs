@@ -1,23 +1,53 @@
 // src/compare.rs
-use crate::election::RawElectionResult;
+use crate::election::{ElectionOutputs, staked_assignments_to_offline_winners};
+use crate::output::report;
 use crate::rpc::RpcClient;
-use crate::storage_keys::plain_key_hex;
-use crate::types::{AccountId, ElectionSnapshot, Hash, OfflineWinner};
+use crate::storage_keys::{session_queued_keys_key, session_validators_key, validator_count_at};
+use crate::types::{
+    AccountId, ElectionSnapshot, Hash, OfflineWinner, VoteWeight, account_formatter,
+};
 use anyhow::{Result, anyhow};
-use parity_scale_codec::Decode;
+use parity_scale_codec::{Compact, Decode};
 use std::collections::{BTreeSet, HashMap};
 
+/// Read `Staking::ValidatorCount` on the relay chain at a given block.
+///
+/// Fails if the item is not set, e.g. once staking has moved to AssetHub.
+pub async fn fetch_relay_desired_validator_count(client: &RpcClient, at: Hash) -> Result<u32> {
+    validator_count_at(client, at).await?.ok_or_else(|| {
+        anyhow!(
+            "Staking::ValidatorCount not set at relay block 0x{}",
+            hex::encode(at)
+        )
+    })
+}
+
 /// Fetch validator set from relay chain `Session::Validators` at a given block.
+///
+/// The whole `Vec` is read in one storage call. Where the relay chain still has
+/// `Staking::ValidatorCount`, the decoded length is checked against it and a mismatch
+/// is reported, since it hints at a wrong storage key or a truncated read.
 pub async fn fetch_relay_session_validators(
     client: &RpcClient,
     at: Hash,
 ) -> Result<Vec<AccountId>> {
-    let key = plain_key_hex("Session", "Validators");
+    let key = session_validators_key();
 
     if let Some(bytes) = client.get_storage(&key, Some(at)).await? {
         let mut slice = &bytes[..];
         let vals: Vec<AccountId> = Decode::decode(&mut slice)
             .map_err(|e| anyhow!("decode Session::Validators: {:?}", e))?;
+
+        match fetch_relay_desired_validator_count(client, at).await {
+            Ok(desired) if desired as usize != vals.len() => eprintln!(
+                "WARN: Session::Validators has {} entries but Staking::ValidatorCount is {}; \
+                 the storage key may be wrong",
+                vals.len(),
+                desired
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::debug!("skipping validator count check: {e}"),
+        }
         Ok(vals)
     } else {
         eprintln!(
@@ -28,6 +58,49 @@ pub async fn fetch_relay_session_validators(
     }
 }
 
+/// Fetch the validators queued to become active in the next session from relay chain
+/// `Session::QueuedKeys` at a given block.
+///
+/// `QueuedKeys` is a `Vec<(ValidatorId, SessionKeys)>`. `SessionKeys` is runtime-specific
+/// but fixed-size, so its length is inferred from the encoded size and the keys are
+/// skipped without decoding them.
+pub async fn fetch_relay_queued_validators(client: &RpcClient, at: Hash) -> Result<Vec<AccountId>> {
+    let key = session_queued_keys_key();
+
+    let Some(bytes) = client.get_storage(&key, Some(at)).await? else {
+        eprintln!(
+            "WARN: Session::QueuedKeys returned None at block 0x{}",
+            hex::encode(at)
+        );
+        return Ok(Vec::new());
+    };
+
+    let mut slice = &bytes[..];
+    let Compact(count) = Compact::<u32>::decode(&mut slice)
+        .map_err(|e| anyhow!("decode Session::QueuedKeys length: {:?}", e))?;
+    let count = count as usize;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if slice.len() % count != 0 || slice.len() / count < 32 {
+        return Err(anyhow!(
+            "Session::QueuedKeys: {} bytes do not split into {} entries",
+            slice.len(),
+            count
+        ));
+    }
+
+    let entry_len = slice.len() / count;
+    Ok(slice
+        .chunks_exact(entry_len)
+        .map(|entry| {
+            entry[..32]
+                .try_into()
+                .expect("entry holds at least 32 bytes")
+        })
+        .collect())
+}
+
 /// Compare two validator sets and return:
 /// (intersection, only_offline, only_onchain).
 pub fn compare_winners_with_chain(
@@ -45,122 +118,315 @@ pub fn compare_winners_with_chain(
     (intersection, only_offline, only_onchain)
 }
 
-/// Format `AccountId` as hex string.
-pub fn fmt_account(id: &AccountId) -> String {
-    format!("0x{}", hex::encode(id))
+/// Kendall's tau-b between the orderings of the validators present in both lists:
+/// `1.0` for the same order, `-1.0` for the reverse, around `0.0` for no relation.
+///
+/// Positions in a list are strict, so there are no ties and tau-b reduces to
+/// `(concordant - discordant) / pairs`. Fewer than two common validators give `0.0`.
+pub fn kendall_tau(offline_ranked: &[AccountId], onchain_ranked: &[AccountId]) -> f64 {
+    let onchain_pos: HashMap<&AccountId, usize> = onchain_ranked
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+    // On-chain positions of the common validators, in offline order.
+    let positions: Vec<usize> = offline_ranked
+        .iter()
+        .filter_map(|v| onchain_pos.get(v).copied())
+        .collect();
+
+    let n = positions.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mut score: i64 = 0;
+    for i in 0..n {
+        for j in i + 1..n {
+            score += if positions[i] < positions[j] { 1 } else { -1 };
+        }
+    }
+    score as f64 / (n * (n - 1) / 2) as f64
+}
+
+/// Zero-based position of `target` in an offline winner ranking.
+pub fn offline_rank_of(target: &AccountId, winners: &[OfflineWinner]) -> Option<usize> {
+    winners.iter().position(|w| w.validator == *target)
 }
 
+/// Print the detailed diff between the offline winner set and a relay validator set.
+///
+/// `all_targets` is the snapshot's target set, to tell on-chain-only validators that
+/// were not even candidates apart. `rank_map` maps validators to their offline
+/// `(rank, support)`, possibly extended past the cutoff (`offline_winners.len()`);
+/// when given, offline-only validators are annotated with rank and support and
+/// on-chain-only validators with the offline rank they reached.
 pub fn compare_with_relay(
-    snapshot: &ElectionSnapshot,
-    res: &RawElectionResult,
-    onchain_validators: &[AccountId],
+    offline_winners: &BTreeSet<AccountId>,
+    all_targets: &BTreeSet<AccountId>,
+    onchain: &BTreeSet<AccountId>,
+    rank_map: Option<&HashMap<AccountId, (usize, u128)>>,
 ) {
-    let offline_winners: Vec<&AccountId> = res.winners.iter().map(|(v, _)| v).collect();
-
-    let offline_set: BTreeSet<AccountId> = offline_winners.iter().cloned().cloned().collect();
-    let onchain_set: BTreeSet<AccountId> = onchain_validators.iter().cloned().collect();
-
-    let only_offline: Vec<AccountId> = offline_set.difference(&onchain_set).cloned().collect();
-    let only_onchain: Vec<AccountId> = onchain_set.difference(&offline_set).cloned().collect();
-
-    let match_count = offline_set.len() - only_offline.len();
-
-    println!(
-        "Comparison with RELAY Session::Validators: match={}, only_offline={}, only_onchain={}",
-        match_count,
-        only_offline.len(),
-        only_onchain.len(),
-    );
-
-    // Detailed diff.
+    let only_offline: Vec<&AccountId> = offline_winners.difference(onchain).collect();
+    let only_onchain: Vec<&AccountId> = onchain.difference(offline_winners).collect();
+    let rank_of = |id: &AccountId| rank_map.and_then(|m| m.get(id)).copied();
 
     if !only_offline.is_empty() {
-        println!("\nValidators only in OFFLINE winners (not on-chain):");
+        report!("\nValidators only in OFFLINE winners (not on-chain):");
         for id in &only_offline {
-            if let Some((idx, (_val, support))) =
-                res.winners.iter().enumerate().find(|(_, (v, _))| v == id)
-            {
-                println!("  rank #{:<3} {} support={}", idx, fmt_account(id), support,);
-            } else {
-                println!("  {}", fmt_account(id));
+            match rank_of(id) {
+                Some((rank, support)) => report!(
+                    "  rank #{:<3} {} support={}",
+                    rank,
+                    account_formatter().fmt(id),
+                    support,
+                ),
+                None => report!("  {}", account_formatter().fmt(id)),
             }
         }
     }
 
     if !only_onchain.is_empty() {
-        println!("\nValidators only in ON-CHAIN winners (not offline):");
+        report!("\nValidators only in ON-CHAIN winners (not offline):");
+        let cutoff = offline_winners.len();
         for id in &only_onchain {
-            let in_snapshot = snapshot.all_targets.iter().any(|val| val == id);
+            let in_snapshot = all_targets.contains(*id);
+            let rank = match (rank_map, rank_of(id)) {
+                _ if !in_snapshot => String::new(),
+                (None, _) => String::new(),
+                (Some(_), Some((idx, _))) if idx == cutoff => {
+                    format!(" offline rank #{idx}, first one beyond the cutoff")
+                }
+                (Some(_), Some((idx, _))) => format!(" offline rank #{idx}"),
+                (Some(_), None) => " not ranked offline".to_string(),
+            };
 
-            println!(
-                "  {} (in snapshot.all_targets: {})",
-                fmt_account(id),
+            report!(
+                "  {} (in snapshot.all_targets: {}){}",
+                account_formatter().fmt(id),
                 if in_snapshot { "yes" } else { "NO" },
+                rank,
             );
         }
     }
 }
 
+/// Side of the offline vs on-chain comparison a boundary validator is found on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundarySide {
+    /// Elected offline but not in `Session::Validators`.
+    OfflineOnly,
+    /// In `Session::Validators` but not elected offline.
+    OnchainOnly,
+}
+
+/// Offline winner ranked close to a mismatched validator.
+#[derive(Clone, Debug)]
+pub struct BoundaryNeighbour {
+    pub rank: usize,
+    pub validator: AccountId,
+    pub support: VoteWeight,
+}
+
+/// A validator that differs between offline and on-chain results.
+#[derive(Clone, Debug)]
+pub struct BoundaryEntry {
+    pub validator: AccountId,
+    pub side: BoundarySide,
+    /// Offline rank and support (`None` for on-chain-only validators).
+    pub rank: Option<usize>,
+    pub support: Option<VoteWeight>,
+    /// Offline winners within `neighborhood` ranks of `rank`, the entry itself included.
+    pub neighbours: Vec<BoundaryNeighbour>,
+}
+
+/// Boundary debugging result of `debug_boundary_ranks`.
+#[derive(Clone, Debug)]
+pub struct BoundaryReport {
+    /// Number of neighbours collected on each side of a mismatched rank.
+    pub neighborhood: usize,
+    /// Offline-only entries first, then on-chain-only entries.
+    pub entries: Vec<BoundaryEntry>,
+}
+
+impl BoundaryReport {
+    pub fn count(&self, side: BoundarySide) -> usize {
+        self.entries.iter().filter(|e| e.side == side).count()
+    }
+}
+
 /// Debug helper for validators that differ between offline and on-chain results.
 ///
 /// - `offline_winners` is the sorted offline winner list.
 /// - `onchain_validators` is the `Session::Validators` list from the relay chain.
-pub fn debug_boundary_ranks(offline_winners: &[OfflineWinner], onchain_validators: &[AccountId]) {
+/// - `neighborhood` is how many offline ranks to collect on each side of a mismatch.
+pub fn debug_boundary_ranks(
+    offline_winners: &[OfflineWinner],
+    onchain_validators: &[AccountId],
+    neighborhood: usize,
+) -> BoundaryReport {
     // Map: validator -> (rank, support).
-    let mut rank_map: HashMap<AccountId, (usize, u128)> = HashMap::new();
+    let mut rank_map: HashMap<AccountId, (usize, VoteWeight)> = HashMap::new();
     for (idx, w) in offline_winners.iter().enumerate() {
-        rank_map.insert(w.validator, (idx, w.support as u128));
+        rank_map.insert(w.validator, (idx, w.support));
     }
 
     let offline_set: BTreeSet<AccountId> = offline_winners.iter().map(|w| w.validator).collect();
     let onchain_set: BTreeSet<AccountId> = onchain_validators.iter().copied().collect();
 
-    let only_offline: Vec<AccountId> = offline_set.difference(&onchain_set).copied().collect();
-    let only_onchain: Vec<AccountId> = onchain_set.difference(&offline_set).copied().collect();
-
-    eprintln!(
-        "BOUNDARY DEBUG: only_offline = {}, only_onchain = {}",
-        only_offline.len(),
-        only_onchain.len()
-    );
-
-    // Details for validators that are only in offline winners.
-    for v in &only_offline {
-        if let Some((rank, support)) = rank_map.get(v) {
-            eprintln!(
-                "  OFFLINE-ONLY 0x{} at offline rank {} with support {}",
-                hex::encode(v),
-                rank,
-                support
-            );
+    let mut entries = Vec::new();
 
-            let start = rank.saturating_sub(3);
-            let end = usize::min(rank + 3, offline_winners.len().saturating_sub(1));
-
-            eprintln!("    Neighbours around that rank:");
-            for i in start..=end {
-                let w = &offline_winners[i];
-                eprintln!(
-                    "      {} rank {:4} 0x{} support={}",
-                    if i == *rank { ">>" } else { "  " },
-                    i,
-                    hex::encode(w.validator),
-                    w.support
+    // Validators that are only in offline winners, with their rank neighbourhood.
+    for v in offline_set.difference(&onchain_set) {
+        let (rank, support) = match rank_map.get(v) {
+            Some((rank, support)) => (Some(*rank), Some(*support)),
+            None => (None, None),
+        };
+
+        let neighbours = match rank {
+            Some(rank) => {
+                let start = rank.saturating_sub(neighborhood);
+                let end = usize::min(
+                    rank.saturating_add(neighborhood),
+                    offline_winners.len().saturating_sub(1),
                 );
+                offline_winners
+                    .iter()
+                    .enumerate()
+                    .take(end + 1)
+                    .skip(start)
+                    .map(|(i, w)| BoundaryNeighbour {
+                        rank: i,
+                        validator: w.validator,
+                        support: w.support,
+                    })
+                    .collect()
             }
-        } else {
-            eprintln!(
-                "  OFFLINE-ONLY 0x{} but not found in rank_map (unexpected)",
-                hex::encode(v)
-            );
-        }
+            None => Vec::new(),
+        };
+
+        entries.push(BoundaryEntry {
+            validator: *v,
+            side: BoundarySide::OfflineOnly,
+            rank,
+            support,
+            neighbours,
+        });
     }
 
     // Validators that are only present on-chain.
-    for v in &only_onchain {
-        eprintln!(
-            "  ONCHAIN-ONLY 0x{} did not appear in offline winners",
-            hex::encode(v)
-        );
+    for v in onchain_set.difference(&offline_set) {
+        entries.push(BoundaryEntry {
+            validator: *v,
+            side: BoundarySide::OnchainOnly,
+            rank: None,
+            support: None,
+            neighbours: Vec::new(),
+        });
+    }
+
+    BoundaryReport {
+        neighborhood,
+        entries,
+    }
+}
+
+/// Support of a validator elected in both of two compared elections.
+#[derive(Clone, Debug)]
+pub struct CommonWinner {
+    pub validator: AccountId,
+    pub support_a: VoteWeight,
+    pub support_b: VoteWeight,
+}
+
+/// Result of comparing two offline elections (e.g. Polkadot vs Kusama, or two rounds).
+#[derive(Clone, Debug)]
+pub struct CrossElectionComparison {
+    /// `desired_targets` of snapshot A.
+    pub desired_a: u32,
+    /// `desired_targets` of snapshot B.
+    pub desired_b: u32,
+    /// Winners present in both results, ordered by their rank in A.
+    pub common: Vec<CommonWinner>,
+    /// Winners only present in A, in A's rank order.
+    pub only_a: Vec<AccountId>,
+    /// Winners only present in B, in B's rank order.
+    pub only_b: Vec<AccountId>,
+}
+
+/// Compare the winner sets and supports of two offline elections.
+pub fn compare_two_offline_elections(
+    snap_a: &ElectionSnapshot,
+    outputs_a: &ElectionOutputs,
+    snap_b: &ElectionSnapshot,
+    outputs_b: &ElectionOutputs,
+) -> CrossElectionComparison {
+    let winners_a = staked_assignments_to_offline_winners(outputs_a, false, false);
+    let winners_b = staked_assignments_to_offline_winners(outputs_b, false, false);
+
+    let support_b: HashMap<AccountId, VoteWeight> =
+        winners_b.iter().map(|w| (w.validator, w.support)).collect();
+    let set_a: BTreeSet<AccountId> = winners_a.iter().map(|w| w.validator).collect();
+
+    let mut common = Vec::new();
+    let mut only_a = Vec::new();
+
+    for w in &winners_a {
+        match support_b.get(&w.validator) {
+            Some(support_b) => common.push(CommonWinner {
+                validator: w.validator,
+                support_a: w.support,
+                support_b: *support_b,
+            }),
+            None => only_a.push(w.validator),
+        }
+    }
+
+    let only_b: Vec<AccountId> = winners_b
+        .iter()
+        .map(|w| w.validator)
+        .filter(|v| !set_a.contains(v))
+        .collect();
+
+    CrossElectionComparison {
+        desired_a: snap_a.desired_targets,
+        desired_b: snap_b.desired_targets,
+        common,
+        only_a,
+        only_b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn winner(id: u8, support: VoteWeight) -> OfflineWinner {
+        OfflineWinner {
+            validator: [id; 32],
+            support,
+            backers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn offline_rank_of_is_zero_based_position() {
+        let winners = vec![winner(1, 30), winner(2, 20), winner(3, 10)];
+        assert_eq!(offline_rank_of(&[1u8; 32], &winners), Some(0));
+        assert_eq!(offline_rank_of(&[3u8; 32], &winners), Some(2));
+        assert_eq!(offline_rank_of(&[4u8; 32], &winners), None);
+    }
+
+    #[test]
+    fn kendall_tau_over_common_validators() {
+        let ids = |b: &[u8]| b.iter().map(|x| [*x; 32]).collect::<Vec<AccountId>>();
+        assert_eq!(kendall_tau(&ids(&[1, 2, 3]), &ids(&[1, 2, 3])), 1.0);
+        assert_eq!(kendall_tau(&ids(&[1, 2, 3]), &ids(&[3, 2, 1])), -1.0);
+        // One of three pairs swapped: (2 - 1) / 3.
+        assert!((kendall_tau(&ids(&[1, 2, 3]), &ids(&[1, 3, 2])) - 1.0 / 3.0).abs() < 1e-12);
+        // Validators in only one list are ignored.
+        assert_eq!(kendall_tau(&ids(&[1, 4, 2]), &ids(&[5, 1, 2])), 1.0);
+        // Fewer than two common validators.
+        assert_eq!(kendall_tau(&ids(&[1, 2]), &ids(&[2, 3])), 0.0);
+        assert_eq!(kendall_tau(&[], &[]), 0.0);
     }
 }
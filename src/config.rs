@@ -0,0 +1,61 @@
+// src/config.rs
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+use crate::types::{BalanceFormatter, SS58_PREFIX_MAX};
+
+/// Defaults for the global CLI flags, read from a TOML file passed via `--config`.
+///
+/// Every key is optional; flags given on the command line take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub ws: Option<String>,
+    pub relay_ws: Option<String>,
+    pub ss58_prefix: Option<u16>,
+    pub decimals: Option<u8>,
+    pub symbol: Option<String>,
+    pub rpc_timeout_secs: Option<u64>,
+    pub max_pages: Option<u32>,
+    pub log_level: Option<String>,
+}
+
+impl Config {
+    /// Read and validate a config file; unknown keys are rejected so typos surface.
+    pub fn load(path: &Path) -> Result<Config> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("reading config file {}", path.display()))?;
+        let config: Config = toml::from_str(&data)
+            .with_context(|| format!("parsing config file {}", path.display()))?;
+
+        if let Some(prefix) = config.ss58_prefix
+            && prefix > SS58_PREFIX_MAX
+        {
+            return Err(anyhow!(
+                "{}: ss58_prefix {} exceeds {}",
+                path.display(),
+                prefix,
+                SS58_PREFIX_MAX
+            ));
+        }
+        if let Some(decimals) = config.decimals
+            && decimals > BalanceFormatter::MAX_DECIMALS
+        {
+            return Err(anyhow!(
+                "{}: decimals {} exceeds {}",
+                path.display(),
+                decimals,
+                BalanceFormatter::MAX_DECIMALS
+            ));
+        }
+        if config.max_pages == Some(0) {
+            return Err(anyhow!("{}: max_pages must be at least 1", path.display()));
+        }
+
+        Ok(config)
+    }
+}
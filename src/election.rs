@@ -1,22 +1,32 @@
 // src/election.rs
 
 use crate::types::{
-    AccountId, ElectionSnapshot, OfflineBacker, OfflineWinner, VoteWeight, VoterSnapshot,
+    AccountId, ElectionSnapshot, OfflineBacker, OfflineRatioAssignment, OfflineRatioShare,
+    OfflineWinner, TargetApproval, VoteWeight, VoterSnapshot, all_unique,
 };
 
 use anyhow::Result;
-use sp_arithmetic::PerU16;
+use rand::Rng;
+use rand::rngs::StdRng;
+use sp_arithmetic::{PerThing, PerU16};
 use sp_npos_elections::{
-    ElectionResult, StakedAssignment, assignment_ratio_to_staked_normalized,
-    assignment_staked_to_ratio_normalized, reduce, seq_phragmen,
+    Assignment, BalancingConfig, ElectionResult, ElectionScore, EvaluateSupport, ExtendedBalance,
+    StakedAssignment, Support, SupportMap, Supports, assignment_ratio_to_staked_normalized,
+    assignment_staked_to_ratio_normalized, phragmms, reduce, seq_phragmen, to_support_map,
+    to_supports,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 /// Raw output of `sp_npos_elections::seq_phragmen`.
 pub type RawElectionResult = ElectionResult<AccountId, PerU16>;
 
 /// Flatten `voter_pages` into a single vector, matching `BaseMiner::mine_solution`.
-fn flatten_voters(snapshot: &ElectionSnapshot) -> Vec<(AccountId, VoteWeight, Vec<AccountId>)> {
+pub(crate) fn flatten_voters(
+    snapshot: &ElectionSnapshot,
+) -> Vec<(AccountId, VoteWeight, Vec<AccountId>)> {
     snapshot
         .voter_pages
         .iter()
@@ -25,30 +35,323 @@ fn flatten_voters(snapshot: &ElectionSnapshot) -> Vec<(AccountId, VoteWeight, Ve
         .collect()
 }
 
+/// Like `flatten_voters`, with the index of the page each voter came from.
+pub(crate) fn flatten_voters_with_page_labels(
+    snapshot: &ElectionSnapshot,
+) -> Vec<(AccountId, VoteWeight, Vec<AccountId>, usize)> {
+    snapshot
+        .voter_pages
+        .iter()
+        .enumerate()
+        .flat_map(|(page_idx, page)| {
+            page.iter()
+                .map(move |v| (v.who, v.weight, v.targets.clone(), page_idx))
+        })
+        .collect()
+}
+
+/// Rescale voter weights as `weight * u32::MAX / total_issuance`, the `CurrencyToVote`
+/// normalization older runtimes applied, so that the whole issuance maps to `u32::MAX`
+/// and relative proportions are kept (up to rounding down).
+///
+/// Current runtimes use `SaturatingCurrencyToVote`, which passes balances through
+/// unscaled (saturating at `u64::MAX`); that is what the snapshot weights already are.
+pub fn normalize_voter_weights(
+    voters: &mut [(AccountId, VoteWeight, Vec<AccountId>)],
+    total_issuance: u128,
+) {
+    for (_, weight, _) in voters.iter_mut() {
+        *weight = normalize_weight(*weight, total_issuance);
+    }
+}
+
+/// Keep only the first `max` targets of each voter, like an on-chain `MaxVotesPerVoter`
+/// lower than what voters nominated. Returns `(truncated voters, dropped targets)`.
+pub fn truncate_voter_targets(
+    voters: &mut [(AccountId, VoteWeight, Vec<AccountId>)],
+    max: usize,
+) -> (usize, usize) {
+    let mut truncated = 0usize;
+    let mut dropped = 0usize;
+    for (_, _, targets) in voters.iter_mut() {
+        if targets.len() > max {
+            truncated += 1;
+            dropped += targets.len() - max;
+            targets.truncate(max);
+        }
+    }
+    (truncated, dropped)
+}
+
+/// Copy of `snapshot` with every voter weight normalized like `normalize_voter_weights`,
+/// to check outputs of a normalized run against.
+pub fn normalize_snapshot_weights(snapshot: &ElectionSnapshot) -> ElectionSnapshot {
+    let mut normalized = snapshot.clone();
+    normalized.checksum = None;
+    for voter in normalized.voter_pages.iter_mut().flatten() {
+        voter.weight = normalize_weight(voter.weight, snapshot.total_issuance);
+    }
+    normalized
+}
+
+fn normalize_weight(weight: VoteWeight, total_issuance: u128) -> VoteWeight {
+    let scaled = (weight as u128).saturating_mul(u32::MAX as u128) / total_issuance.max(1);
+    scaled.min(VoteWeight::MAX as u128) as VoteWeight
+}
+
+/// Maximum per-target support difference tolerated across `reduce` (rounding only).
+const REDUCE_SUPPORT_TOLERANCE: ExtendedBalance = 1;
+
 /// Canonical election outputs:
 /// - `raw`: winners and ratio assignments (`PerU16`).
 /// - `staked_assignments`: same assignments in `VoteWeight` units,
+/// - `reduced_edges`: number of edges removed by all `reduce` steps (`None` if none ran).
+/// - `computation_time_ms`: time spent in the election algorithm and `reduce` steps,
+///   excluding input preparation and assignment conversions.
+/// - `round`: EPMB round of the snapshot the election ran on.
+/// - `saturated_shares`: staked shares above `u64::MAX`, which are clamped when
+///   converted to `VoteWeight` (see `staked_assignments_to_offline_winners`).
+/// - `timings`: per-step breakdown of the run that produced these outputs (a cached
+///   result keeps the timings of the run that computed it).
+/// - `algorithm`: name of the election algorithm that ran (`seq_phragmen` / `phragmms`).
 pub struct ElectionOutputs {
     pub raw: RawElectionResult,
     pub staked_assignments: Option<Vec<StakedAssignment<AccountId>>>,
+    pub reduced_edges: Option<u32>,
+    pub computation_time_ms: u64,
+    pub round: u32,
+    pub saturated_shares: usize,
+    pub timings: ElectionTimings,
+    pub algorithm: &'static str,
+}
+
+/// Milliseconds spent in each step of `run_with_stake`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ElectionTimings {
+    /// Flattening voters, including weight normalization and target truncation.
+    pub flatten_voters_ms: u64,
+    /// The election algorithm itself (`seq_phragmen` / `phragmms`).
+    pub elect_ms: u64,
+    /// `assignment_ratio_to_staked_normalized`, over all conversions.
+    pub ratio_to_staked_ms: u64,
+    /// All `reduce` passes, excluding their verification.
+    pub reduce_ms: u64,
+    /// `assignment_staked_to_ratio_normalized`, over all conversions.
+    pub staked_to_ratio_ms: u64,
+}
+
+// `ElectionResult` is not `Clone`, so this is spelled out.
+impl Clone for ElectionOutputs {
+    fn clone(&self) -> Self {
+        Self {
+            raw: RawElectionResult {
+                winners: self.raw.winners.clone(),
+                assignments: self.raw.assignments.clone(),
+            },
+            staked_assignments: self.staked_assignments.clone(),
+            reduced_edges: self.reduced_edges,
+            computation_time_ms: self.computation_time_ms,
+            round: self.round,
+            saturated_shares: self.saturated_shares,
+            timings: self.timings,
+            algorithm: self.algorithm,
+        }
+    }
+}
+
+impl ElectionOutputs {
+    /// Whether exactly `desired_targets` winners were elected.
+    pub fn is_complete(&self, desired_targets: u32) -> bool {
+        self.raw.winners.len() == desired_targets as usize
+    }
 }
 
 /// Run `seq_phragmen` and additionally compute canonical staked assignments.
+///
+/// `seq_phragmen` elects one winner per iteration, so `max_iterations` caps the number
+/// of winners; `None` runs the natural `desired_targets` iterations.
+///
+/// `pre_reduce` additionally reduces the first staked assignments and round-trips them
+/// through ratio space before the `do_reduce` step, reproducing older pipelines that
+/// reduced at more than one stage.
+///
+/// `normalize_weights` rescales voter weights with `normalize_voter_weights` first;
+/// supports and staked assignments are then in normalized units.
+///
+/// `max_targets_per_voter` truncates target lists with `truncate_voter_targets`.
+///
+/// The last `ELECTION_CACHE_CAPACITY` results are memoized in `ELECTION_CACHE` per
+/// snapshot checksum and options, unless disabled with
+/// `set_election_cache_enabled(false)`.
 pub fn run_offline_election_with_stake(
     snapshot: &ElectionSnapshot,
     do_reduce: bool,
+    pre_reduce: bool,
+    max_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
 ) -> Result<ElectionOutputs> {
+    let run = || {
+        run_with_stake(
+            snapshot,
+            do_reduce,
+            pre_reduce,
+            max_iterations,
+            normalize_weights,
+            max_targets_per_voter,
+            "seq_phragmen",
+            seq_phragmen::<AccountId, PerU16>,
+        )
+    };
+    if !ELECTION_CACHE_ENABLED.load(Ordering::Relaxed) {
+        return run();
+    }
+
+    let key = ElectionCacheKey {
+        snapshot_checksum: snapshot.compute_checksum(),
+        do_reduce,
+        pre_reduce,
+        max_iterations,
+        normalize_weights,
+        max_targets_per_voter,
+    };
+    let cache = ELECTION_CACHE.get_or_init(Default::default);
+    if let Some((_, cached)) = cache
+        .lock()
+        .expect("election cache poisoned")
+        .iter()
+        .find(|(k, _)| *k == key)
+    {
+        eprintln!("[info] reusing cached seq_phragmen result for this snapshot");
+        return Ok(cached.clone());
+    }
+    let outputs = run()?;
+    let mut cache = cache.lock().expect("election cache poisoned");
+    if cache.len() == ELECTION_CACHE_CAPACITY {
+        cache.pop_front();
+    }
+    cache.push_back((key, outputs.clone()));
+    Ok(outputs)
+}
+
+/// Everything `run_offline_election_with_stake` output depends on.
+#[derive(Clone, PartialEq, Eq)]
+struct ElectionCacheKey {
+    /// `ElectionSnapshot::compute_checksum` of the input snapshot.
+    snapshot_checksum: String,
+    do_reduce: bool,
+    pre_reduce: bool,
+    max_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
+}
+
+/// Results kept by `ELECTION_CACHE`; the oldest is evicted first.
+const ELECTION_CACHE_CAPACITY: usize = 4;
+
+/// Process-wide memo of the last `run_offline_election_with_stake` results.
+type ElectionCache = Mutex<VecDeque<(ElectionCacheKey, ElectionOutputs)>>;
+static ELECTION_CACHE: OnceLock<ElectionCache> = OnceLock::new();
+static ELECTION_CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn result caching in `run_offline_election_with_stake` on or off (`--no-cache`).
+pub fn set_election_cache_enabled(enabled: bool) {
+    ELECTION_CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+static REDUCE_VERIFY_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn the post-`reduce` edge count and idempotency checks on or off
+/// (`--skip-reduce-verify`).
+pub fn set_reduce_verify_enabled(enabled: bool) {
+    REDUCE_VERIFY_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Same pipeline as `run_offline_election_with_stake`, using `phragmms` instead of
+/// `seq_phragmen` (without balancing).
+pub fn run_phragmms_with_stake(
+    snapshot: &ElectionSnapshot,
+    do_reduce: bool,
+    pre_reduce: bool,
+    max_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
+) -> Result<ElectionOutputs> {
+    run_with_stake(
+        snapshot,
+        do_reduce,
+        pre_reduce,
+        max_iterations,
+        normalize_weights,
+        max_targets_per_voter,
+        "phragmms",
+        phragmms::<AccountId, PerU16>,
+    )
+}
+
+/// Signature shared by `seq_phragmen` and `phragmms`.
+type ElectionFn = fn(
+    usize,
+    Vec<AccountId>,
+    Vec<(AccountId, VoteWeight, Vec<AccountId>)>,
+    Option<BalancingConfig>,
+) -> Result<RawElectionResult, sp_npos_elections::Error>;
+
+/// Run `elect` on the flattened snapshot and compute canonical staked assignments.
+#[allow(clippy::too_many_arguments)] // private; mirrors the public wrappers' flags
+fn run_with_stake(
+    snapshot: &ElectionSnapshot,
+    do_reduce: bool,
+    pre_reduce: bool,
+    max_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
+    name: &'static str,
+    elect: ElectionFn,
+) -> Result<ElectionOutputs> {
+    let mut timings = ElectionTimings::default();
+
     // Flatten voters and clone targets.
+    let flatten_started = Instant::now();
     let all_targets: Vec<AccountId> = snapshot.all_targets.clone();
-    let all_voters: Vec<(AccountId, VoteWeight, Vec<AccountId>)> = flatten_voters(snapshot);
-    let to_elect = snapshot.desired_targets as usize;
+    let mut all_voters: Vec<(AccountId, VoteWeight, Vec<AccountId>)> = flatten_voters(snapshot);
+    if normalize_weights {
+        normalize_voter_weights(&mut all_voters, snapshot.total_issuance);
+    }
+    if let Some(max) = max_targets_per_voter {
+        let (truncated, dropped) = truncate_voter_targets(&mut all_voters, max);
+        eprintln!(
+            "[info] truncated {} voter target lists to {} targets ({} targets dropped)",
+            truncated, max, dropped
+        );
+    }
+    timings.flatten_voters_ms = flatten_started.elapsed().as_millis() as u64;
+    // Neither algorithm takes an iteration count; both elect one winner per
+    // round, so truncating `to_elect` is equivalent.
+    let desired = snapshot.desired_targets as usize;
+    let to_elect = max_iterations.map_or(desired, |n| n.min(desired));
 
-    // Run seq_phragmen.
+    // Run the election algorithm.
+    let elect_started = Instant::now();
     let ElectionResult {
         winners,
         assignments,
-    } = seq_phragmen::<AccountId, PerU16>(to_elect, all_targets.clone(), all_voters.clone(), None)
-        .map_err(|e| anyhow::anyhow!("seq_phragmen failed: {:?}", e))?;
+    } = elect(to_elect, all_targets.clone(), all_voters.clone(), None).map_err(|e| {
+        // Input sizes narrow down whether this is a snapshot problem
+        // (empty/truncated) or a genuine algorithmic edge case.
+        let edges: usize = all_voters.iter().map(|(_, _, t)| t.len()).sum();
+        anyhow::anyhow!(
+            "{} failed: {:?} (to_elect={}, targets={}, voters={}, edges={})",
+            name,
+            e,
+            to_elect,
+            all_targets.len(),
+            all_voters.len(),
+            edges,
+        )
+    })?;
+    let mut computation = elect_started.elapsed();
+    timings.elect_ms = computation.as_millis() as u64;
 
     // Build `stake_of` from the flattened voter list using `VoteWeight` (u64).
     let mut stake_map: HashMap<AccountId, VoteWeight> = HashMap::new();
@@ -58,20 +361,77 @@ pub fn run_offline_election_with_stake(
 
     let stake_of = move |who: &AccountId| -> VoteWeight { *stake_map.get(who).unwrap_or(&0u64) };
 
+    report_short_ratio_sums(&assignments);
+
     // Convert ratio assignments -> staked assignments (canonical helper).
+    let mut ratio_to_staked = Duration::ZERO;
+    let mut staked_to_ratio_time = Duration::ZERO;
+    let mut reduce_time = Duration::ZERO;
+    let conversion_started = Instant::now();
     let mut staked: Vec<StakedAssignment<AccountId>> =
         assignment_ratio_to_staked_normalized(assignments, &stake_of).map_err(|e| {
             anyhow::anyhow!("assignment_ratio_to_staked_normalized failed: {:?}", e)
         })?;
+    ratio_to_staked += conversion_started.elapsed();
 
-    // Optional global reduction, matching miner behavior.
-    if do_reduce {
-        let _reduced_edges = reduce(&mut staked);
-    }
+    // Optional early reduction followed by a ratio round trip (historical pipelines).
+    let pre_reduced_edges = if pre_reduce {
+        let supports_before = to_support_map(&staked);
+        let edges_before = edge_count(&staked);
+        let reduce_started = Instant::now();
+        let removed = reduce(&mut staked);
+        let reduce_elapsed = reduce_started.elapsed();
+        reduce_time += reduce_elapsed;
+        computation += reduce_elapsed;
+        check_supports_preserved(&supports_before, &to_support_map(&staked))?;
+        if REDUCE_VERIFY_ENABLED.load(Ordering::Relaxed) {
+            check_reduce_converged(&mut staked, edges_before, removed)?;
+        }
+
+        let conversion_started = Instant::now();
+        let ratio = staked_to_ratio(&staked)?;
+        staked_to_ratio_time += conversion_started.elapsed();
+        let conversion_started = Instant::now();
+        staked = assignment_ratio_to_staked_normalized(ratio, &stake_of).map_err(|e| {
+            anyhow::anyhow!("assignment_ratio_to_staked_normalized failed: {:?}", e)
+        })?;
+        ratio_to_staked += conversion_started.elapsed();
+        Some(removed)
+    } else {
+        None
+    };
+
+    // Optional global reduction, matching miner behavior. `reduce` only removes
+    // redundant edges, so every target's support must be unchanged by it.
+    let reduced_edges = if do_reduce {
+        let supports_before = to_support_map(&staked);
+        let edges_before = edge_count(&staked);
+        let reduce_started = Instant::now();
+        let removed = reduce(&mut staked);
+        let reduce_elapsed = reduce_started.elapsed();
+        reduce_time += reduce_elapsed;
+        computation += reduce_elapsed;
+        check_supports_preserved(&supports_before, &to_support_map(&staked))?;
+        if REDUCE_VERIFY_ENABLED.load(Ordering::Relaxed) {
+            check_reduce_converged(&mut staked, edges_before, removed)?;
+        }
+        Some(removed + pre_reduced_edges.unwrap_or(0))
+    } else {
+        pre_reduced_edges
+    };
 
     // Convert staked assignments back to ratio space (as in `BaseMiner`).
-    let final_ratio_assignments = assignment_staked_to_ratio_normalized(staked.clone())
-        .map_err(|e| anyhow::anyhow!("assignment_staked_to_ratio_normalized failed: {:?}", e))?;
+    let conversion_started = Instant::now();
+    let final_ratio_assignments = staked_to_ratio(&staked)?;
+    staked_to_ratio_time += conversion_started.elapsed();
+    timings.ratio_to_staked_ms = ratio_to_staked.as_millis() as u64;
+    timings.reduce_ms = reduce_time.as_millis() as u64;
+    timings.staked_to_ratio_ms = staked_to_ratio_time.as_millis() as u64;
+    let saturated_shares = staked
+        .iter()
+        .flat_map(|a| a.distribution.iter())
+        .filter(|(_, share)| *share > u64::MAX as ExtendedBalance)
+        .count();
 
     Ok(ElectionOutputs {
         raw: RawElectionResult {
@@ -79,29 +439,224 @@ pub fn run_offline_election_with_stake(
             assignments: final_ratio_assignments,
         },
         staked_assignments: Some(staked),
+        reduced_edges,
+        computation_time_ms: computation.as_millis() as u64,
+        round: snapshot.round,
+        saturated_shares,
+        timings,
+        algorithm: name,
+    })
+}
+
+/// Log nominators whose `PerU16` ratios sum to more than 10 parts below `u16::MAX`.
+///
+/// A normalized assignment sums to `u16::MAX` (off by at most one from rounding); a
+/// larger shortfall is stake that `assignment_ratio_to_staked_normalized` loses.
+fn report_short_ratio_sums(assignments: &[Assignment<AccountId, PerU16>]) {
+    const TOLERANCE: u32 = 10;
+    for a in assignments.iter().filter(|a| !a.distribution.is_empty()) {
+        let sum: u32 = a
+            .distribution
+            .iter()
+            .map(|(_, p)| p.deconstruct() as u32)
+            .sum();
+        if sum + TOLERANCE < u16::MAX as u32 {
+            eprintln!(
+                "[warn] nominator 0x{} PerU16 ratios sum to {} (expected {})",
+                hex::encode(a.who),
+                sum,
+                u16::MAX
+            );
+        }
+    }
+}
+
+/// `assignment_staked_to_ratio_normalized` over a copy of `staked`, with an error that
+/// names the first nominator whose assignment fails to normalize on its own, its
+/// distribution sum and whether it has a zero entry (the usual cause).
+fn staked_to_ratio(
+    staked: &[StakedAssignment<AccountId>],
+) -> Result<Vec<Assignment<AccountId, PerU16>>> {
+    assignment_staked_to_ratio_normalized(staked.to_vec()).map_err(|e| {
+        let culprit = staked
+            .iter()
+            .find(|a| assignment_staked_to_ratio_normalized::<_, PerU16>(vec![(*a).clone()]).is_err());
+        match culprit {
+            Some(a) => {
+                let sum = a
+                    .distribution
+                    .iter()
+                    .fold(0 as ExtendedBalance, |acc, (_, s)| acc.saturating_add(*s));
+                let has_zero = a.distribution.iter().any(|(_, s)| *s == 0);
+                anyhow::anyhow!(
+                    "assignment_staked_to_ratio_normalized failed on {} assignments: {:?}; \
+                     first failing nominator 0x{} (distribution sum {}, {} targets, zero entry: {})",
+                    staked.len(),
+                    e,
+                    hex::encode(a.who),
+                    sum,
+                    a.distribution.len(),
+                    has_zero
+                )
+            }
+            None => anyhow::anyhow!(
+                "assignment_staked_to_ratio_normalized failed on {} assignments: {:?}; \
+                 no single assignment fails on its own",
+                staked.len(),
+                e
+            ),
+        }
     })
 }
 
+/// Total number of nominator -> target edges in `staked`.
+fn edge_count(staked: &[StakedAssignment<AccountId>]) -> usize {
+    staked.iter().map(|a| a.distribution.len()).sum()
+}
+
+/// Error unless a `reduce` pass that removed `removed` edges from `edges_before` left
+/// no more edges than it started with, and a second pass over `staked` removes none.
+fn check_reduce_converged(
+    staked: &mut Vec<StakedAssignment<AccountId>>,
+    edges_before: usize,
+    removed: u32,
+) -> Result<()> {
+    let edges_after = edge_count(staked);
+    if edges_after > edges_before {
+        return Err(anyhow::anyhow!(
+            "reduce increased the edge count: before={} after={} (reported removed={})",
+            edges_before,
+            edges_after,
+            removed,
+        ));
+    }
+
+    let removed_again = reduce(staked);
+    if removed_again != 0 {
+        return Err(anyhow::anyhow!(
+            "reduce is not idempotent: first pass {} -> {} edges (reported removed={}), \
+             second pass removed {} more ({} -> {})",
+            edges_before,
+            edges_after,
+            removed,
+            removed_again,
+            edges_after,
+            edge_count(staked),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Error if any target's total support differs by more than rounding between
+/// `before` and `after`.
+fn check_supports_preserved(
+    before: &SupportMap<AccountId>,
+    after: &SupportMap<AccountId>,
+) -> Result<()> {
+    let targets: BTreeSet<&AccountId> = before.keys().chain(after.keys()).collect();
+
+    for target in targets {
+        let b = before.get(target).map_or(0, |s| s.total);
+        let a = after.get(target).map_or(0, |s| s.total);
+        if b.abs_diff(a) > REDUCE_SUPPORT_TOLERANCE {
+            return Err(anyhow::anyhow!(
+                "reduce changed support of 0x{}: before={} after={}",
+                hex::encode(target),
+                b,
+                a,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// `ElectionScore` of the final staked assignments, evaluated as the runtime does
+/// (`to_supports(..).evaluate()`).
+pub fn election_score(outputs: &ElectionOutputs) -> ElectionScore {
+    let staked = outputs
+        .staked_assignments
+        .as_ref()
+        .expect("election_score called without staked_assignments");
+
+    to_supports(staked).evaluate()
+}
+
+/// Render the final ratio assignments (`raw.assignments`) with `PerU16` ratios as `f64`.
+///
+/// This is the fractional split of each voter before it is turned into stake.
+pub fn ratio_assignments_for_inspection(outputs: &ElectionOutputs) -> Vec<OfflineRatioAssignment> {
+    outputs
+        .raw
+        .assignments
+        .iter()
+        .map(|a| OfflineRatioAssignment {
+            voter: a.who,
+            distribution: a
+                .distribution
+                .iter()
+                .map(|(target, ratio)| OfflineRatioShare {
+                    target: *target,
+                    ratio: ratio.deconstruct() as f64 / PerU16::ACCURACY as f64,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
 /// Build `OfflineWinner` list from canonical staked assignments.
 ///
 /// Uses the output of `run_offline_election_with_stake`:
 /// - `support` is the sum of stake shares in `VoteWeight` units.
 /// - `backers` is the distribution of those stake shares.
 /// - winners are ordered by their election rank (`raw.winners` order).
-pub fn staked_assignments_to_offline_winners(outputs: &ElectionOutputs) -> Vec<OfflineWinner> {
+///
+/// Panics if `raw.winners` contains the same validator twice, since the second
+/// occurrence would otherwise silently show up with zero backers.
+///
+/// Backers with a zero share are counted in a warning and dropped, unless
+/// `keep_zero_weight_backers` is set: `reduce` should never leave a zero-weight
+/// edge, so a nonzero count hints at a precision or reduce anomaly.
+///
+/// A nominator listed more than once for the same validator (not expected after
+/// `reduce`) becomes a single backer with the shares summed; merges are counted in
+/// a warning.
+///
+/// A winner without any assignment share is kept with `support = 0` unless
+/// `filter_zero_support` is set, in which case it is dropped with a warning. Either
+/// way such a winner points at degenerate input or an election bug.
+pub fn staked_assignments_to_offline_winners(
+    outputs: &ElectionOutputs,
+    filter_zero_support: bool,
+    keep_zero_weight_backers: bool,
+) -> Vec<OfflineWinner> {
     let staked = outputs
         .staked_assignments
         .as_ref()
         .expect("staked_assignments_to_offline_winners called without staked_assignments");
 
-    use std::collections::HashMap;
+    let winner_ids: Vec<AccountId> = outputs.raw.winners.iter().map(|(v, _)| *v).collect();
+    assert!(
+        all_unique(&winner_ids),
+        "election winners contain duplicate validators; seq_phragmen output is corrupt"
+    );
 
     // Aggregate by validator.
     let mut by_validator: HashMap<AccountId, OfflineWinner> = HashMap::new();
+    let mut zero_weight_backers = 0usize;
+    let mut duplicate_backers = 0usize;
 
     for assignment in staked {
         let nominator = assignment.who;
         for (validator, share) in &assignment.distribution {
+            if *share == 0 {
+                zero_weight_backers += 1;
+                if !keep_zero_weight_backers {
+                    continue;
+                }
+            }
+
             let entry = by_validator
                 .entry(*validator)
                 .or_insert_with(|| OfflineWinner {
@@ -110,16 +665,54 @@ pub fn staked_assignments_to_offline_winners(outputs: &ElectionOutputs) -> Vec<O
                     backers: Vec::new(),
                 });
 
-            // Election weights are < total issuance < 2^64, so this cast is safe.
-            let share_u64 = (*share as u128).min(u64::MAX as u128) as u64;
+            // Election weights are < total issuance < 2^64, so this should never
+            // saturate; if it does, clamp instead of truncating.
+            if *share > u64::MAX as u128 {
+                eprintln!(
+                    "[warn] share {} of nominator 0x{} for validator 0x{} exceeds u64::MAX; clamped",
+                    share,
+                    hex::encode(nominator),
+                    hex::encode(validator),
+                );
+            }
+            let share_u64 = (*share).min(u64::MAX as u128) as u64;
             entry.support = entry.support.saturating_add(share_u64);
-            entry.backers.push(OfflineBacker {
-                who: nominator,
-                weight: share_u64,
-            });
+            if let Some(existing) = entry.backers.iter_mut().find(|b| b.who == nominator) {
+                existing.weight = existing.weight.saturating_add(share_u64);
+                duplicate_backers += 1;
+            } else {
+                entry.backers.push(OfflineBacker {
+                    who: nominator,
+                    weight: share_u64,
+                });
+            }
         }
     }
 
+    if zero_weight_backers > 0 {
+        eprintln!(
+            "[warn] {} {} zero-weight backers {} the winner list",
+            if keep_zero_weight_backers {
+                "kept"
+            } else {
+                "dropped"
+            },
+            zero_weight_backers,
+            if keep_zero_weight_backers {
+                "in"
+            } else {
+                "from"
+            },
+        );
+    }
+
+    if duplicate_backers > 0 {
+        eprintln!(
+            "[warn] merged {} duplicate nominator -> validator edges into existing backers",
+            duplicate_backers
+        );
+    }
+
     // Order winners according to `raw.winners` (election rank).
     let mut ordered: Vec<OfflineWinner> = Vec::with_capacity(outputs.raw.winners.len());
 
@@ -135,12 +728,133 @@ pub fn staked_assignments_to_offline_winners(outputs: &ElectionOutputs) -> Vec<O
         }
     }
 
+    if filter_zero_support {
+        let before = ordered.len();
+        ordered.retain(|w| w.support > 0);
+        let dropped = before - ordered.len();
+        if dropped > 0 {
+            eprintln!(
+                "[warn] dropped {} elected validators with zero support",
+                dropped
+            );
+        }
+    }
+
     ordered
 }
 
+/// Approval stake of every snapshot target: the sum of the weights of all voters that
+/// nominate it, regardless of how the election splits their stake.
+///
+/// Ordered by descending approval stake; `elected` marks the targets in `raw.winners`.
+pub fn target_approvals(
+    snapshot: &ElectionSnapshot,
+    outputs: &ElectionOutputs,
+) -> Vec<TargetApproval> {
+    let approvals = snapshot.total_vote_weight_by_target();
+
+    let elected: BTreeSet<AccountId> = outputs.raw.winners.iter().map(|(v, _)| *v).collect();
+    let mut out: Vec<TargetApproval> = approvals
+        .into_iter()
+        .map(|(target, approval_stake)| TargetApproval {
+            target,
+            approval_stake,
+            elected: elected.contains(&target),
+        })
+        .collect();
+    out.sort_by_key(|t| std::cmp::Reverse(t.approval_stake));
+    out
+}
+
+/// Headline statistics of an offline election.
+#[derive(Clone, Debug)]
+pub struct ElectionSummary {
+    /// Sum of all snapshot voter weights.
+    pub total_voter_weight: VoteWeight,
+    /// Sum of all staked assignment shares.
+    pub total_assigned_weight: VoteWeight,
+    /// `total_assigned_weight / total_voter_weight`.
+    ///
+    /// Values below ~0.99 usually mean voters whose targets were all unelected.
+    pub assigned_fraction: f64,
+    pub winner_count: usize,
+    pub total_support: VoteWeight,
+    pub min_support: VoteWeight,
+    pub max_support: VoteWeight,
+}
+
+/// Voters with no share in any staked assignment, i.e. none of their targets won,
+/// heaviest first. Their stake shows up in no validator's exposure.
+pub fn find_unassigned_voters(
+    snapshot: &ElectionSnapshot,
+    outputs: &ElectionOutputs,
+) -> Vec<(AccountId, VoteWeight)> {
+    let assigned: BTreeSet<AccountId> = outputs
+        .staked_assignments
+        .iter()
+        .flatten()
+        .filter(|a| a.distribution.iter().any(|(_, share)| *share > 0))
+        .map(|a| a.who)
+        .collect();
+
+    let mut unassigned: Vec<(AccountId, VoteWeight)> = snapshot
+        .voter_pages
+        .iter()
+        .flatten()
+        .filter(|v| !assigned.contains(&v.who))
+        .map(|v| (v.who, v.weight))
+        .collect();
+    unassigned.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+    unassigned
+}
+
+/// Compute an `ElectionSummary` from a snapshot and its election outputs.
+pub fn compute_election_summary(
+    snapshot: &ElectionSnapshot,
+    outputs: &ElectionOutputs,
+) -> ElectionSummary {
+    let total_voter_weight: VoteWeight = snapshot
+        .voter_pages
+        .iter()
+        .flatten()
+        .fold(0, |acc: VoteWeight, v| acc.saturating_add(v.weight));
+
+    let total_assigned_weight: VoteWeight = outputs
+        .staked_assignments
+        .iter()
+        .flatten()
+        .flat_map(|a| a.distribution.iter())
+        .fold(0, |acc: VoteWeight, (_, share)| {
+            acc.saturating_add((*share).min(u64::MAX as u128) as u64)
+        });
+
+    let assigned_fraction = if total_voter_weight == 0 {
+        0.0
+    } else {
+        total_assigned_weight as f64 / total_voter_weight as f64
+    };
+
+    let winners = staked_assignments_to_offline_winners(outputs, false, false);
+    let supports = winners.iter().map(|w| w.support);
+
+    ElectionSummary {
+        total_voter_weight,
+        total_assigned_weight,
+        assigned_fraction,
+        winner_count: winners.len(),
+        total_support: supports
+            .clone()
+            .fold(0, |acc: VoteWeight, s| acc.saturating_add(s)),
+        min_support: supports.clone().min().unwrap_or(0),
+        max_support: supports.max().unwrap_or(0),
+    }
+}
+
 /// Internal consistency check for staked assignments:
 /// - For each nominator, `sum(share)` should be <= `stake_of(nominator)` and
 ///   typically equal up to rounding.
+/// - For each winner, the shares assigned to it must sum exactly to the `support`
+///   reported by `staked_assignments_to_offline_winners`; mismatches are printed.
 /// - Sum over all validator supports should match sum of all nominators' stake
 ///   up to rounding.
 pub fn verify_staked_assignments_internal(
@@ -152,8 +866,6 @@ pub fn verify_staked_assignments_internal(
         .as_ref()
         .ok_or_else(|| anyhow::anyhow!("No staked_assignments present"))?;
 
-    use std::collections::HashMap;
-
     // Map: nominator -> stake (from snapshot).
     let mut stake_of: HashMap<AccountId, VoteWeight> = HashMap::new();
     for page in &snapshot.voter_pages {
@@ -169,7 +881,7 @@ pub fn verify_staked_assignments_internal(
         let mut total: VoteWeight = 0;
 
         for (_validator, share) in &ass.distribution {
-            let share_u64 = (*share as u128).min(u64::MAX as u128) as u64;
+            let share_u64 = (*share).min(u64::MAX as u128) as u64;
             total = total.saturating_add(share_u64);
         }
 
@@ -183,8 +895,37 @@ pub fn verify_staked_assignments_internal(
         }
     }
 
+    let winners = staked_assignments_to_offline_winners(outputs, false, false);
+
+    // Per-validator totals: assignment shares summed here must match the `support`
+    // that `staked_assignments_to_offline_winners` computed for the same validator.
+    let mut assigned_to: HashMap<AccountId, ExtendedBalance> = HashMap::new();
+    for ass in staked {
+        for (validator, share) in &ass.distribution {
+            *assigned_to.entry(*validator).or_default() += *share;
+        }
+    }
+    let mut mismatched = 0usize;
+    for w in &winners {
+        let assigned = assigned_to.get(&w.validator).copied().unwrap_or(0);
+        if assigned != w.support as ExtendedBalance {
+            eprintln!(
+                "    SUPPORT-MISMATCH 0x{} assignment_sum={} winner_support={}",
+                hex::encode(w.validator),
+                assigned,
+                w.support,
+            );
+            mismatched += 1;
+        }
+    }
+    if mismatched > 0 {
+        return Err(anyhow::anyhow!(
+            "{} validators have a winner support different from their assignment sum",
+            mismatched
+        ));
+    }
+
     // Global totals: sum of supports vs sum of all nominator weights.
-    let winners = staked_assignments_to_offline_winners(outputs);
     let mut total_support: VoteWeight = 0;
     for w in &winners {
         total_support = total_support.saturating_add(w.support);
@@ -202,3 +943,439 @@ pub fn verify_staked_assignments_internal(
 
     Ok(())
 }
+
+/// A voter that could still move stake between two of its winners and lower the
+/// larger of the two supports by more than the balancing tolerance.
+#[derive(Clone, Debug)]
+pub struct BalancingViolation {
+    pub voter: AccountId,
+    /// Winner the stake would move away from (the higher support).
+    pub from: AccountId,
+    /// Winner the stake would move to (the lower support).
+    pub to: AccountId,
+    /// `min(share on from, (support(from) - support(to)) / 2)`: how much the larger
+    /// support would drop.
+    pub shift: ExtendedBalance,
+}
+
+/// Check the balancing invariant on the staked assignments: no voter can shift
+/// stake from one of its winners to another to reduce the larger of the two
+/// supports by more than `tolerance`.
+///
+/// This is the convergence condition of `sp_npos_elections::balance` with
+/// `BalancingConfig::tolerance`. A voter's winners are the elected targets it
+/// nominates in `snapshot`, including those `reduce` left without an edge. Only
+/// each voter's largest shift is reported; the result is ordered by `shift`
+/// descending.
+///
+/// The pipeline elects without balancing, so violations measure how far the
+/// solution is from balanced rather than indicating an error.
+pub fn find_balancing_violations(
+    snapshot: &ElectionSnapshot,
+    outputs: &ElectionOutputs,
+    tolerance: ExtendedBalance,
+) -> Result<Vec<BalancingViolation>> {
+    let staked = outputs
+        .staked_assignments
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No staked_assignments present"))?;
+
+    // Every winner has a support, even if no edge is left after `reduce`.
+    let mut supports: HashMap<AccountId, ExtendedBalance> =
+        outputs.raw.winners.iter().map(|(w, _)| (*w, 0)).collect();
+    for ass in staked {
+        for (validator, share) in &ass.distribution {
+            *supports.entry(*validator).or_default() += *share;
+        }
+    }
+
+    let targets_of: HashMap<AccountId, &[AccountId]> = snapshot
+        .voter_pages
+        .iter()
+        .flatten()
+        .map(|v| (v.who, v.targets.as_slice()))
+        .collect();
+
+    let mut violations = Vec::new();
+    for ass in staked {
+        let Some(targets) = targets_of.get(&ass.who) else {
+            continue;
+        };
+
+        let mut worst: Option<BalancingViolation> = None;
+        for (from, share) in &ass.distribution {
+            let support_from = supports[from];
+            for to in targets.iter().filter(|t| *t != from) {
+                let Some(&support_to) = supports.get(to) else {
+                    continue;
+                };
+                if support_from <= support_to {
+                    continue;
+                }
+                let shift = (*share).min((support_from - support_to) / 2);
+                if shift > tolerance && worst.as_ref().is_none_or(|w| shift > w.shift) {
+                    worst = Some(BalancingViolation {
+                        voter: ass.who,
+                        from: *from,
+                        to: *to,
+                        shift,
+                    });
+                }
+            }
+        }
+        violations.extend(worst);
+    }
+
+    violations.sort_by_key(|v| std::cmp::Reverse(v.shift));
+    Ok(violations)
+}
+
+/// Rebuild `Supports` from an exported `OfflineWinner` list: `total` is the winner's
+/// `support` and `voters` are its backers, in `ExtendedBalance`.
+///
+/// Lets an exported result be scored and checked without the original `ElectionOutputs`.
+pub fn winners_to_supports(winners: &[OfflineWinner]) -> Supports<AccountId> {
+    winners
+        .iter()
+        .map(|w| {
+            (
+                w.validator,
+                Support {
+                    total: w.support as ExtendedBalance,
+                    voters: w
+                        .backers
+                        .iter()
+                        .map(|b| (b.who, b.weight as ExtendedBalance))
+                        .collect(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Feasibility of an exported winner list against its snapshot:
+/// - at most `desired_targets` unique winners, all in `all_targets`,
+/// - every winner's `support` equals the sum of its backers,
+/// - every backer is a snapshot voter that nominated that winner,
+/// - no voter is assigned more than its weight in total.
+pub fn verify_winners_against_snapshot(
+    snapshot: &ElectionSnapshot,
+    winners: &[OfflineWinner],
+) -> Result<()> {
+    let winner_ids: Vec<AccountId> = winners.iter().map(|w| w.validator).collect();
+    if !all_unique(&winner_ids) {
+        return Err(anyhow::anyhow!("winner list contains duplicate validators"));
+    }
+    if winners.len() > snapshot.desired_targets as usize {
+        return Err(anyhow::anyhow!(
+            "{} winners exceed desired_targets={}",
+            winners.len(),
+            snapshot.desired_targets
+        ));
+    }
+
+    let targets: BTreeSet<&AccountId> = snapshot.all_targets.iter().collect();
+    let voters: HashMap<AccountId, &VoterSnapshot> = snapshot
+        .voter_pages
+        .iter()
+        .flatten()
+        .map(|v| (v.who, v))
+        .collect();
+    let mut assigned: HashMap<AccountId, VoteWeight> = HashMap::new();
+
+    for w in winners {
+        if !targets.contains(&w.validator) {
+            return Err(anyhow::anyhow!(
+                "winner 0x{} is not a snapshot target",
+                hex::encode(w.validator)
+            ));
+        }
+
+        let backed: VoteWeight = w
+            .backers
+            .iter()
+            .fold(0, |acc: VoteWeight, b| acc.saturating_add(b.weight));
+        if backed != w.support {
+            return Err(anyhow::anyhow!(
+                "winner 0x{} has support {} but its backers sum to {}",
+                hex::encode(w.validator),
+                w.support,
+                backed
+            ));
+        }
+
+        for b in &w.backers {
+            let voter = voters.get(&b.who).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "backer 0x{} of 0x{} is not a snapshot voter",
+                    hex::encode(b.who),
+                    hex::encode(w.validator)
+                )
+            })?;
+            if !voter.targets.contains(&w.validator) {
+                return Err(anyhow::anyhow!(
+                    "backer 0x{} does not nominate 0x{}",
+                    hex::encode(b.who),
+                    hex::encode(w.validator)
+                ));
+            }
+            let total = assigned.entry(b.who).or_insert(0);
+            *total = total.saturating_add(b.weight);
+        }
+    }
+
+    for (who, total) in &assigned {
+        let weight = voters[who].weight;
+        if *total > weight {
+            return Err(anyhow::anyhow!(
+                "voter 0x{} assigned {} but has weight {}",
+                hex::encode(who),
+                total,
+                weight
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Outcome of re-running the election with randomly perturbed voter weights.
+pub struct SensitivityReport {
+    pub iterations: usize,
+    /// Targets elected in every iteration.
+    pub stable: Vec<AccountId>,
+    /// Targets elected in some but not all iterations, with their election count.
+    pub boundary: Vec<(AccountId, usize)>,
+    /// Targets never elected.
+    pub never: Vec<AccountId>,
+}
+
+/// Run the election `iterations` times with every voter weight scaled by a factor drawn
+/// uniformly from `[1 - pct/100, 1 + pct/100]`.
+///
+/// Quantifies how robust the winner set is to small changes in voter stake.
+/// `pct` must be finite and in `(0, 100]`. The election flags are the same as for
+/// `run_offline_election_with_stake`, so the perturbed runs match the user's run.
+#[allow(clippy::too_many_arguments)] // forwards the run-offline election flags
+pub fn run_sensitivity_analysis(
+    snapshot: &ElectionSnapshot,
+    do_reduce: bool,
+    pre_reduce: bool,
+    max_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
+    pct: f64,
+    iterations: usize,
+    rng: &mut StdRng,
+) -> Result<SensitivityReport> {
+    if !(pct.is_finite() && pct > 0.0 && pct <= 100.0) {
+        return Err(anyhow::anyhow!(
+            "sensitivity percentage must be in (0, 100], got {}",
+            pct
+        ));
+    }
+    let spread = pct / 100.0;
+
+    let mut elected_count: BTreeMap<AccountId, usize> = BTreeMap::new();
+
+    for _ in 0..iterations {
+        let mut perturbed = snapshot.clone();
+        for page in &mut perturbed.voter_pages {
+            for v in page.iter_mut() {
+                let factor: f64 = rng.gen_range((1.0 - spread)..=(1.0 + spread));
+                // `as` saturates on float -> int casts.
+                v.weight = (v.weight as f64 * factor) as VoteWeight;
+            }
+        }
+
+        // Perturbed snapshots never repeat, so bypass the election cache.
+        let outputs = run_with_stake(
+            &perturbed,
+            do_reduce,
+            pre_reduce,
+            max_iterations,
+            normalize_weights,
+            max_targets_per_voter,
+            "seq_phragmen",
+            seq_phragmen::<AccountId, PerU16>,
+        )?;
+        for (validator, _) in &outputs.raw.winners {
+            *elected_count.entry(*validator).or_insert(0) += 1;
+        }
+    }
+
+    let mut stable = Vec::new();
+    let mut boundary = Vec::new();
+    let mut never = Vec::new();
+
+    for target in &snapshot.all_targets {
+        match elected_count.get(target).copied().unwrap_or(0) {
+            0 => never.push(*target),
+            n if n == iterations => stable.push(*target),
+            n => boundary.push((*target, n)),
+        }
+    }
+
+    // Most frequently elected boundary validators first.
+    boundary.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+    Ok(SensitivityReport {
+        iterations,
+        stable,
+        boundary,
+        never,
+    })
+}
+
+/// Winner sets of the same snapshot under several election variants.
+#[derive(Clone, Debug)]
+pub struct CrossCheckReport {
+    /// `(variant label, winner set)` for every variant run.
+    pub variants: Vec<(String, BTreeSet<AccountId>)>,
+    /// Validators elected by some but not all variants.
+    pub divergent: BTreeSet<AccountId>,
+}
+
+/// Run the election with `reduce` on and off, for both `seq_phragmen` and `phragmms`,
+/// and collect validators whose election depends on the variant.
+///
+/// Only winner sets are compared; supports may legitimately differ between variants.
+pub fn cross_check_winners(
+    snapshot: &ElectionSnapshot,
+    max_iterations: Option<usize>,
+) -> Result<CrossCheckReport> {
+    let mut variants = Vec::new();
+
+    for do_reduce in [true, false] {
+        let seq = run_offline_election_with_stake(
+            snapshot,
+            do_reduce,
+            false,
+            max_iterations,
+            false,
+            None,
+        )?;
+        let mms = run_phragmms_with_stake(snapshot, do_reduce, false, max_iterations, false, None)?;
+
+        for (algorithm, outputs) in [("seq_phragmen", seq), ("phragmms", mms)] {
+            let winners: BTreeSet<AccountId> =
+                outputs.raw.winners.iter().map(|(v, _)| *v).collect();
+            variants.push((format!("{} reduce={}", algorithm, do_reduce), winners));
+        }
+    }
+
+    let union: BTreeSet<AccountId> = variants
+        .iter()
+        .flat_map(|(_, w)| w.iter().copied())
+        .collect();
+    let divergent = union
+        .into_iter()
+        .filter(|v| variants.iter().any(|(_, w)| !w.contains(v)))
+        .collect();
+
+    Ok(CrossCheckReport {
+        variants,
+        divergent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ElectionOutputs` electing `winners` with the given staked assignments; the
+    /// ratio assignments are not needed by the winner builder and stay empty.
+    fn outputs(
+        winners: &[AccountId],
+        staked: Vec<(AccountId, Vec<(AccountId, u128)>)>,
+    ) -> ElectionOutputs {
+        ElectionOutputs {
+            raw: RawElectionResult {
+                winners: winners.iter().map(|w| (*w, 0)).collect(),
+                assignments: Vec::new(),
+            },
+            staked_assignments: Some(
+                staked
+                    .into_iter()
+                    .map(|(who, distribution)| StakedAssignment { who, distribution })
+                    .collect(),
+            ),
+            reduced_edges: None,
+            computation_time_ms: 0,
+            round: 0,
+            saturated_shares: 0,
+            timings: ElectionTimings::default(),
+            algorithm: "seq_phragmen",
+        }
+    }
+
+    #[test]
+    fn duplicate_backer_edges_are_merged() {
+        let (v, a) = ([1u8; 32], [2u8; 32]);
+        let out = outputs(&[v], vec![(a, vec![(v, 10), (v, 5)])]);
+
+        let winners = staked_assignments_to_offline_winners(&out, false, false);
+        assert_eq!(winners[0].support, 15);
+        assert_eq!(winners[0].backers.len(), 1);
+        assert_eq!(winners[0].backers[0].weight, 15);
+    }
+
+    #[test]
+    fn balancing_violation_reports_largest_shift() {
+        let (v1, v2, a, b) = ([1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]);
+        let snapshot = ElectionSnapshot {
+            schema_version: crate::types::SNAPSHOT_SCHEMA_VERSION,
+            at: [0; 32],
+            round: 0,
+            total_issuance: 0,
+            desired_targets: 2,
+            all_targets: vec![v1, v2],
+            voter_pages: vec![vec![
+                VoterSnapshot {
+                    who: a,
+                    weight: 100,
+                    targets: vec![v1, v2],
+                },
+                VoterSnapshot {
+                    who: b,
+                    weight: 20,
+                    targets: vec![v2],
+                },
+            ]],
+            checksum: None,
+        };
+        // Supports v1 = 100, v2 = 20: `a` could move min(100, 80 / 2) = 40 to v2.
+        let out = outputs(&[v1, v2], vec![(a, vec![(v1, 100)]), (b, vec![(v2, 20)])]);
+
+        let violations = find_balancing_violations(&snapshot, &out, 0).unwrap();
+        assert_eq!(violations.len(), 1);
+        let viol = &violations[0];
+        assert_eq!(
+            (viol.voter, viol.from, viol.to, viol.shift),
+            (a, v1, v2, 40)
+        );
+
+        assert!(
+            find_balancing_violations(&snapshot, &out, 40)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn zero_share_backers_are_dropped_unless_kept() {
+        let (v, a, b) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let out = outputs(&[v], vec![(a, vec![(v, 10)]), (b, vec![(v, 0)])]);
+
+        let dropped = staked_assignments_to_offline_winners(&out, false, false);
+        assert_eq!(dropped[0].support, 10);
+        assert_eq!(dropped[0].backers.len(), 1);
+        assert_eq!(dropped[0].backers[0].who, a);
+
+        let kept = staked_assignments_to_offline_winners(&out, false, true);
+        assert_eq!(kept[0].support, 10);
+        assert_eq!(kept[0].backers.len(), 2);
+        assert_eq!(kept[0].backers[1].who, b);
+        assert_eq!(kept[0].backers[1].weight, 0);
+    }
+}
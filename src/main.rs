@@ -2,45 +2,141 @@
 mod ah_multi_block_source;
 mod asset_hub;
 mod compare;
+mod config;
 mod election;
 mod nominator_debug;
 mod offchain_exposures;
 mod onchain_exposures;
+mod output;
 mod rpc;
 mod storage_keys;
 mod types;
+mod verifier;
 
-use crate::ah_multi_block_source::AhMultiBlockSource;
-use crate::compare::{compare_with_relay, debug_boundary_ranks, fetch_relay_session_validators};
+use crate::ah_multi_block_source::{AhMultiBlockSource, SnapshotAvailability};
+use crate::compare::{
+    BoundaryReport, BoundarySide, compare_two_offline_elections, compare_winners_with_chain,
+    compare_with_relay, debug_boundary_ranks, fetch_relay_queued_validators,
+    fetch_relay_session_validators, kendall_tau, offline_rank_of,
+};
+use crate::config::Config;
 use crate::election::{
-    run_offline_election_with_stake, staked_assignments_to_offline_winners,
-    verify_staked_assignments_internal,
+    compute_election_summary, cross_check_winners, election_score, find_balancing_violations,
+    find_unassigned_voters, flatten_voters_with_page_labels, normalize_snapshot_weights,
+    ratio_assignments_for_inspection, run_offline_election_with_stake, run_sensitivity_analysis,
+    set_election_cache_enabled, set_reduce_verify_enabled, staked_assignments_to_offline_winners,
+    target_approvals, verify_staked_assignments_internal, verify_winners_against_snapshot,
+    winners_to_supports,
+};
+use crate::nominator_debug::{
+    build_offline_nom_view, build_onchain_nom_view, debug_nominator, exposure_churn,
+    find_validators_for_nominator,
+};
+use crate::offchain_exposures::{
+    RuntimeExposureMapExt, build_runtime_exposures_from_staked, project_rewards,
 };
-use crate::nominator_debug::{build_offline_nom_view, build_onchain_nom_view, debug_nominator};
-use crate::offchain_exposures::build_runtime_exposures_from_staked;
 use crate::onchain_exposures::{
-    fetch_active_era_at, fetch_current_era_at, fetch_onchain_exposures_for_era,
-    fetch_overviews_for_validators, flatten_onchain_backers,
+    OnchainExposureOverview, fetch_active_era_at, fetch_all_overviews,
+    fetch_all_overviews_for_eras, fetch_current_era_at, fetch_nominations_for_account,
+    fetch_onchain_exposures_for_era, fetch_validator_commissions, flatten_onchain_backers,
+    verify_onchain_exposure_totals,
+};
+use crate::output::{
+    ExposureSummary, MarkdownReport, RelaySummary, RunSummary, RunTimings, ScoreSummary,
+    SnapshotSummary, TimingsSummary, report, set_report_to_stderr, snapshot_output,
+    winners_address_list, write_winners_jsonl,
 };
-use crate::rpc::RpcClient;
-use crate::storage_keys::planning_era_at_ah_block;
-use crate::types::{AccountId, Balance, Hash, snapshot_from_json, snapshot_to_json};
+use crate::rpc::{BlockSpec, RpcClient};
+use crate::storage_keys::{
+    active_era_at_ah_block, outdated_pallet_versions, planning_era_at_ah_block,
+    session_queued_changed_at, staking_ledger_at, total_issuance_at, unregistered_validators_at,
+    validator_count_at,
+};
+use crate::types::{
+    AccountFormatter, AccountId, Balance, BalanceFormatter, ElectionSnapshot, Hash, OfflineWinner,
+    SS58_PREFIX_MAX, account_formatter, balance_formatter, init_account_formatter,
+    init_balance_formatter, snapshot_from_json, snapshot_from_json_unverified,
+};
+use crate::verifier::fetch_queued_winners;
 
 use subxt::{OnlineClient, config::PolkadotConfig};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::collections::{BTreeMap, BTreeSet};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use sp_npos_elections::{ElectionScore, EvaluateSupport};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Default upper bound for number of pages in EPMB snapshots (`--max-pages`).
+/// AssetHub typically uses few pages; [0..max_pages) is scanned.
+const DEFAULT_MAX_PAGES: u32 = 32;
+
+/// SS58 prefix of `--winners-list` addresses without `--ss58-prefix` (Polkadot).
+const DEFAULT_WINNERS_LIST_SS58_PREFIX: u16 = 0;
+
+/// Default `--token-decimals` / `--token-symbol` (Polkadot).
+const DEFAULT_TOKEN_DECIMALS: u8 = 10;
+const DEFAULT_TOKEN_SYMBOL: &str = "DOT";
+
+/// `--help` section describing the `--config` file.
+const CONFIG_HELP: &str = "\
+Config file (--config <path>):
+  A TOML file with defaults for the global flags. Flags on the command line take
+  precedence over the file, which takes precedence over ASSET_HUB_WS / RELAY_WS /
+  RUST_LOG and the built-in defaults. All keys are optional, unknown keys are errors.
+
+  Example .offline-election.toml:
+
+    ws = \"wss://assethub-polkadot-rpc.polkadot.io\"
+    relay_ws = \"wss://rpc-polkadot.luckyfriday.io\"
+    ss58_prefix = 0
+    decimals = 10
+    symbol = \"DOT\"
+    rpc_timeout_secs = 120
+    max_pages = 32
+    log_level = \"info\"";
+
+/// Number of perturbed election runs performed by `--sensitivity`.
+const SENSITIVITY_ITERATIONS: usize = 10;
+
+/// Default RNG seed, so identical inputs always give identical outputs.
+const DEFAULT_SEED: u64 = 0;
+
+/// How `run-offline` prints the offline winners (`--format`).
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum WinnersFormat {
+    /// Human-readable winner list on stderr.
+    Text,
+    /// One JSON object per winner per line (NDJSON) on stdout.
+    Jsonl,
+}
 
-/// Upper bound for number of pages in EPMB snapshots.
-/// AssetHub typically uses few pages; [0..MAX_PAGES) is scanned.
-const MAX_PAGES: u32 = 32;
+/// Source of `desired_targets` for an offline run.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DesiredSource {
+    /// Value stored in the snapshot (`DesiredTargets(round)` at fetch time).
+    Snapshot,
+    /// `Staking::ValidatorCount` read at the snapshot block.
+    ValidatorCount,
+    /// Explicit `--desired-targets` value.
+    Override,
+}
 
 #[derive(Parser)]
-#[command(name = "offline-election-ah", version)]
+#[command(name = "offline-election-ah", version, after_help = CONFIG_HELP)]
 struct Cli {
+    /// TOML file with defaults for the global flags (see the end of `--help`).
+    #[arg(global = true, long)]
+    config: Option<PathBuf>,
+
     /// WS endpoint of Asset Hub node.
     ///
     /// If not provided, the value from `ASSET_HUB_WS` is used.
@@ -53,23 +149,90 @@ struct Cli {
     #[arg(global = true, long)]
     relay_ws: Option<String>,
 
+    /// Seed for any RNG used (e.g. `--sensitivity` sampling).
+    ///
+    /// The effective seed is printed so a run can be reproduced exactly.
+    #[arg(global = true, long, default_value_t = DEFAULT_SEED)]
+    seed: u64,
+
+    /// Use HTTP instead of WebSocket for raw JSON-RPC reads; `--ws`/`--relay-ws`
+    /// then hold HTTP URLs. Subxt-based reads still require a WS endpoint.
+    #[arg(global = true, long)]
+    http: bool,
+
+    /// Display accounts as SS58 addresses with this network prefix
+    /// (e.g. `0` Polkadot, `2` Kusama) instead of `0x` hex.
+    #[arg(global = true, long, value_parser = clap::value_parser!(u16).range(0..=SS58_PREFIX_MAX as i64))]
+    ss58_prefix: Option<u16>,
+
+    /// Token decimals used to display balances in `--debug-exposures` output
+    /// [default: 10].
+    #[arg(global = true, long, value_parser = clap::value_parser!(u8).range(0..=BalanceFormatter::MAX_DECIMALS as i64))]
+    token_decimals: Option<u8>,
+
+    /// Token symbol used to display balances in `--debug-exposures` output
+    /// [default: DOT].
+    #[arg(global = true, long)]
+    token_symbol: Option<String>,
+
+    /// Timeout in seconds for each raw JSON-RPC request [default: 60].
+    #[arg(global = true, long)]
+    rpc_timeout_secs: Option<u64>,
+
+    /// Number of EPMB snapshot pages scanned [default: 32].
+    #[arg(global = true, long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_pages: Option<u32>,
+
+    /// Maximum number of validators whose on-chain exposure pages are fetched
+    /// concurrently.
+    #[arg(global = true, long, default_value_t = 16)]
+    max_concurrent_rpc: usize,
+
+    /// Log filter, e.g. `debug` or `offline_election_tool_rework=trace`; overrides `RUST_LOG`.
+    #[arg(global = true, long)]
+    log_level: Option<String>,
+
     #[command(subcommand)]
     cmd: Commands,
 }
 
+impl Cli {
+    /// Fill every global flag not given on the command line from `config`.
+    fn apply_config(&mut self, config: Config) {
+        self.ws = self.ws.take().or(config.ws);
+        self.relay_ws = self.relay_ws.take().or(config.relay_ws);
+        self.ss58_prefix = self.ss58_prefix.or(config.ss58_prefix);
+        self.token_decimals = self.token_decimals.or(config.decimals);
+        self.token_symbol = self.token_symbol.take().or(config.symbol);
+        self.rpc_timeout_secs = self.rpc_timeout_secs.or(config.rpc_timeout_secs);
+        self.max_pages = self.max_pages.or(config.max_pages);
+        self.log_level = self.log_level.take().or(config.log_level);
+    }
+}
+
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once at startup
 enum Commands {
     /// Fetch on-chain data at a block and save multi-block snapshot as JSON.
     FetchSnapshot {
-        /// Block number on AssetHub; omit for best block.
+        /// Block number on AssetHub, or `finalized`; omit for best block.
         #[arg(long)]
-        block: Option<u32>,
+        block: Option<BlockSpec>,
 
-        /// Output JSON file.
+        /// Output JSON file, or a directory to write `<snapshot_id>.json` into.
         #[arg(long)]
         out: PathBuf,
+
+        /// Give up on a snapshot page after this many seconds; any timed-out page fails
+        /// the fetch once the remaining pages have been read.
+        #[arg(long, default_value_t = 30)]
+        page_timeout_secs: u64,
     },
 
+    /// Follow finalized AssetHub blocks until a complete snapshot is available,
+    /// then print that block.
+    WaitForSnapshot,
+
     /// Run offline election from a previously saved snapshot JSON.
     RunOffline {
         /// Snapshot JSON file.
@@ -80,6 +243,32 @@ enum Commands {
         #[arg(long)]
         compare_block: Option<u32>,
 
+        /// Optional AssetHub block number at which to read the verifier's queued
+        /// solution for the snapshot round, compared against the offline winners
+        /// (and the relay validator set when `--compare-block` is also given).
+        #[arg(long)]
+        queued_block: Option<u32>,
+
+        /// Number of offline ranks shown on each side of a validator that differs
+        /// from the relay validator set (used with `--compare-block`).
+        #[arg(long, default_value_t = 3)]
+        boundary_neighborhood: usize,
+
+        /// With `--compare-block`, print only the match/only_offline/only_onchain
+        /// counts, without per-validator lists or boundary debugging.
+        #[arg(long)]
+        count_only: bool,
+
+        /// With `--compare-block`, skip the extra election run that places
+        /// on-chain-only validators in the offline ranking beyond the cutoff.
+        #[arg(long)]
+        no_extended_ranking: bool,
+
+        /// With `--compare-block`, compare against relay `Session::QueuedKeys` (the
+        /// validators queued for the next session) instead of `Session::Validators`.
+        #[arg(long)]
+        compare_queued: bool,
+
         /// Enable exposure and nominator distribution debugging.
         #[arg(long)]
         debug_exposures: bool,
@@ -94,10 +283,278 @@ enum Commands {
         #[arg(long)]
         exposure_era: Option<u32>,
 
+        /// Fail instead of warning when `ErasStakersOverview.nominator_count` disagrees
+        /// with the flattened paged exposures (an incomplete page fetch).
+        #[arg(long)]
+        strict_exposures: bool,
+
+        /// List validators whose flattened on-chain exposure differs from the
+        /// `ErasStakersOverview` total by more than this amount (plancks).
+        #[arg(long, default_value_t = 0)]
+        exposure_tolerance: u128,
+
         /// Whether to run the offline election with global reduction (`reduce` step).
         /// Defaults to `true`.
         #[arg(long, default_value_t = true)]
         reduce: bool,
+
+        /// Also reduce the initial staked assignments and round-trip them through
+        /// ratio space before the `--reduce` step (double reduction, as in older code).
+        #[arg(long)]
+        pre_reduce: bool,
+
+        /// Rescale voter weights to `weight * u32::MAX / total_issuance` before the
+        /// election, as older `CurrencyToVote` implementations did. Supports are then
+        /// in normalized units. Not applied to `--sensitivity` / `--cross-check` runs.
+        #[arg(long)]
+        normalize_weights: bool,
+
+        /// Keep only the first `n` targets of each voter, simulating an on-chain
+        /// `MaxVotesPerVoter` lower than what voters nominated. Not applied to
+        /// `--sensitivity` / `--cross-check` runs.
+        #[arg(long, value_name = "N")]
+        max_targets_per_voter: Option<usize>,
+
+        /// Merge all voter pages into a single (deduplicated) page before running the election.
+        #[arg(long)]
+        merge_pages: bool,
+
+        /// File with one account (hex) per line to remove from the targets and from every
+        /// voter's target list before the election, e.g. to simulate a chill or slash.
+        /// Blank lines and lines starting with `#` are ignored.
+        #[arg(long, value_name = "FILE")]
+        exclude_validators: Option<PathBuf>,
+
+        /// Keep only the `n` targets with the most nominating weight (and drop the rest
+        /// from voter target lists) before the election.
+        #[arg(long, value_name = "N")]
+        trim_targets: Option<usize>,
+
+        /// Where `desired_targets` comes from.
+        #[arg(long, value_enum, default_value_t = DesiredSource::Snapshot)]
+        desired_source: DesiredSource,
+
+        /// Explicit `desired_targets` (used with `--desired-source override`).
+        #[arg(long)]
+        desired_targets: Option<u32>,
+
+        /// Re-run the election with voter weights randomly scaled by up to ±`pct` percent
+        /// (`0 < pct <= 100`) and report stable, boundary and never-elected validators.
+        #[arg(long, value_name = "PCT", value_parser = parse_sensitivity_pct)]
+        sensitivity: Option<f64>,
+
+        /// Fail if the solution's minimal support is below this value.
+        #[arg(long)]
+        assert_min_support: Option<u128>,
+
+        /// Fail unless the solution's score is at least `minimal,sum,sum_sq`
+        /// (minimal and sum must be >=, sum_sq must be <=).
+        #[arg(long, value_parser = parse_score)]
+        assert_score: Option<ElectionScore>,
+
+        /// Re-run the election with `reduce` on/off and with `seq_phragmen`/`phragmms`,
+        /// and fail if the winner sets differ.
+        #[arg(long)]
+        cross_check: bool,
+
+        /// Stop the election after this many iterations (one winner each);
+        /// defaults to the snapshot's `desired_targets`.
+        #[arg(long, value_name = "N")]
+        max_election_iterations: Option<usize>,
+
+        /// Write the final ratio (`PerU16`) assignments of every voter as JSON.
+        #[arg(long)]
+        ratio_assignments_out: Option<PathBuf>,
+
+        /// Exit with status 2 if fewer than `desired_targets` validators are elected,
+        /// instead of only warning.
+        #[arg(long)]
+        require_complete_solution: bool,
+
+        /// Write every snapshot target with its approval stake and whether it was
+        /// elected, ranked by approval stake. CSV if the path ends in `.csv`, else JSON.
+        #[arg(long)]
+        targets_out: Option<PathBuf>,
+
+        /// Drop elected validators that ended up with zero support (no assigned
+        /// stake) from the winner list, with a warning.
+        #[arg(long)]
+        filter_zero_support: bool,
+
+        /// Keep backers with a zero share in the winner list instead of dropping them
+        /// (they are counted in a warning either way).
+        #[arg(long)]
+        keep_zero_weight_backers: bool,
+
+        /// List voters none of whose targets were elected: count, total weight and
+        /// up to 10 of the heaviest.
+        #[arg(long)]
+        report_unassigned: bool,
+
+        /// Check that no voter could shift stake between two of its winners to lower
+        /// the larger support by more than this tolerance, and list the voters that can.
+        #[arg(long, value_name = "TOLERANCE")]
+        check_balancing: Option<u128>,
+
+        /// Always recompute elections instead of reusing an identical earlier run of
+        /// this process (for debugging).
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Write the offline winners with their supports and backers as JSON
+        /// (input of `verify-winners`).
+        #[arg(long)]
+        winners_out: Option<PathBuf>,
+
+        /// Write only the elected validator addresses, one per line in election rank
+        /// order, as SS58 (`--ss58-prefix`, default Polkadot `0`).
+        #[arg(long)]
+        winners_list: Option<PathBuf>,
+
+        /// How to print the offline winners: a text list on stderr, or `jsonl` to
+        /// stream one JSON object per winner per line to stdout.
+        #[arg(long, value_enum, default_value_t = WinnersFormat::Text)]
+        format: WinnersFormat,
+
+        /// Write snapshot metadata, score, comparison results and timings of this run
+        /// as a single JSON document.
+        #[arg(long)]
+        summary_out: Option<PathBuf>,
+
+        /// Write a Markdown report (overview, winners, score and comparison results)
+        /// for sharing.
+        #[arg(long)]
+        report_md: Option<PathBuf>,
+
+        /// List the validators this nominator backs in the offline solution with the
+        /// assigned stake, support rank and (with `--compare-block`) relay membership.
+        #[arg(long, value_parser = parse_account)]
+        find_validators_for: Option<AccountId>,
+
+        /// Load the snapshot even if its `checksum` does not match its content
+        /// (for hand-edited snapshots).
+        #[arg(long)]
+        skip_checksum: bool,
+
+        /// Skip the checks that `reduce` did not add edges and that a second pass
+        /// removes nothing (saves one `reduce` pass per reduction).
+        #[arg(long)]
+        skip_reduce_verify: bool,
+
+        /// Print a table with the time spent in each step when the run completes.
+        #[arg(long)]
+        timing: bool,
+    },
+
+    /// Check an exported winner list against its snapshot and print its score,
+    /// without re-running the election.
+    VerifyWinners {
+        /// Snapshot JSON file the winners were computed from.
+        #[arg(long)]
+        snapshot: PathBuf,
+
+        /// Winners JSON file written by `run-offline --winners-out`.
+        #[arg(long)]
+        winners: PathBuf,
+    },
+
+    /// Check that every winner of a reference result (e.g. the on-chain elected
+    /// set) is a candidate in the snapshot's `all_targets`.
+    CheckCandidates {
+        /// Snapshot JSON file.
+        #[arg(long)]
+        input: PathBuf,
+
+        /// File with one reference winner (hex) per line; blank lines and lines
+        /// starting with `#` are ignored.
+        #[arg(long, value_name = "FILE")]
+        reference: PathBuf,
+    },
+
+    /// Run two offline elections from snapshot JSONs and compare their winners.
+    ///
+    /// Useful as a cross-check between chains (e.g. Polkadot vs Kusama) or rounds.
+    CompareElections {
+        /// First snapshot JSON file (A).
+        #[arg(long)]
+        snapshot_a: PathBuf,
+
+        /// Second snapshot JSON file (B).
+        #[arg(long)]
+        snapshot_b: PathBuf,
+
+        /// Whether to run both elections with global reduction (`reduce` step).
+        #[arg(long, default_value_t = true)]
+        reduce: bool,
+    },
+
+    /// Run two offline elections from snapshot JSONs and compare their exposures.
+    ///
+    /// Fully offline: reports per-validator backer churn and stake deltas between rounds.
+    CompareExposures {
+        /// First snapshot JSON file (A).
+        #[arg(long)]
+        a: PathBuf,
+
+        /// Second snapshot JSON file (B).
+        #[arg(long)]
+        b: PathBuf,
+
+        /// Whether to run both elections with global reduction (`reduce` step).
+        #[arg(long, default_value_t = true)]
+        reduce: bool,
+    },
+
+    /// Compare one nominator's snapshot entry with its on-chain `Staking::Nominators` targets.
+    TraceAccount {
+        /// Snapshot JSON file.
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Nominator account as `0x`-prefixed hex.
+        #[arg(long, value_parser = parse_account)]
+        account: AccountId,
+
+        /// AssetHub block number to read nominations at; defaults to the snapshot block.
+        #[arg(long)]
+        block: Option<u32>,
+    },
+
+    /// Project each winner's share of an era reward from its offline support and
+    /// on-chain commission, split into validator take and nominator payout.
+    ProjectRewards {
+        /// Snapshot JSON file.
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Era reward to distribute, in planck.
+        #[arg(long)]
+        era_reward: Balance,
+
+        /// AssetHub block number to read `Staking::Validators` commission at;
+        /// defaults to the snapshot block.
+        #[arg(long)]
+        block: Option<u32>,
+
+        /// Whether to run the election with global reduction (`reduce` step).
+        #[arg(long, default_value_t = true)]
+        reduce: bool,
+    },
+
+    /// Fetch on-chain `ErasStakersOverview` for several eras and show each
+    /// validator's exposure across them.
+    ExposureHistory {
+        /// AssetHub block number to read at (must still hold all requested eras).
+        #[arg(long)]
+        block: u32,
+
+        /// Comma-separated era indices, e.g. `1500,1501,1502`.
+        #[arg(long, value_delimiter = ',', required = true)]
+        eras: Vec<u32>,
+
+        /// Maximum number of eras fetched concurrently.
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
     },
 }
 
@@ -106,84 +563,620 @@ async fn main() -> Result<()> {
     // Load environment variables from `.env` if present.
     dotenvy::dotenv().ok();
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    if let Some(path) = &cli.config {
+        let config = Config::load(path)?;
+        cli.apply_config(config);
+    }
+
+    // Diagnostics go to stderr, filtered by `--log-level`, else `RUST_LOG`
+    // (e.g. `RUST_LOG=debug`).
+    let filter = match &cli.log_level {
+        Some(level) => tracing_subscriber::EnvFilter::try_new(level)
+            .map_err(|e| anyhow::anyhow!("invalid log level {:?}: {}", level, e))?,
+        None => tracing_subscriber::EnvFilter::from_default_env(),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+
+    init_account_formatter(AccountFormatter {
+        ss58_prefix: cli.ss58_prefix,
+    });
+    init_balance_formatter(BalanceFormatter::new(
+        cli.token_decimals.unwrap_or(DEFAULT_TOKEN_DECIMALS),
+        cli.token_symbol.as_deref().unwrap_or(DEFAULT_TOKEN_SYMBOL),
+    ));
+    if let Some(secs) = cli.rpc_timeout_secs {
+        RpcClient::set_request_timeout(Duration::from_secs(secs));
+    }
+
+    let mut report = RunReport::new();
+    let result = run(cli, &mut report).await;
+    report.print(if result.is_ok() { "ok" } else { "error" });
+    result
+}
 
+/// Work counters printed as one `[run]` line when a command finishes.
+struct RunReport {
+    started: Instant,
+    voters: usize,
+    targets: usize,
+    winners: usize,
+}
+
+impl RunReport {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            voters: 0,
+            targets: 0,
+            winners: 0,
+        }
+    }
+
+    /// Record the size of a snapshot the command worked on.
+    fn add_snapshot(&mut self, snapshot: &ElectionSnapshot) {
+        self.voters += snapshot.voter_pages.iter().map(Vec::len).sum::<usize>();
+        self.targets += snapshot.all_targets.len();
+    }
+
+    fn print(&self, status: &str) {
+        let rpc = RpcClient::stats();
+        eprintln!(
+            "[run] wall={:.2}s rpc_calls={} rpc_bytes={} voters={} targets={} winners={} status={}",
+            self.started.elapsed().as_secs_f64(),
+            rpc.calls,
+            rpc.bytes,
+            self.voters,
+            self.targets,
+            self.winners,
+            status
+        );
+    }
+}
+
+async fn run(cli: Cli, run_report: &mut RunReport) -> Result<()> {
     // Resolve AssetHub WS endpoint:
-    //   1. CLI `--ws`
+    //   1. CLI `--ws`, else `ws` from `--config`
     //   2. `ASSET_HUB_WS` env var
     let ws = cli.ws.clone().unwrap_or_else(|| {
         std::env::var("ASSET_HUB_WS").expect("ASSET_HUB_WS must be set or --ws provided")
     });
 
     // Resolve relay WS endpoint:
-    //   1. CLI `--relay-ws`
+    //   1. CLI `--relay-ws`, else `relay_ws` from `--config`
     //   2. `RELAY_WS` env var (optional; only required when comparison is used)
     let relay_ws: Option<String> = cli
         .relay_ws
         .clone()
         .or_else(|| std::env::var("RELAY_WS").ok());
 
+    let max_pages = cli.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+
     match cli.cmd {
-        Commands::FetchSnapshot { block, out } => {
-            // Resolve block number → hash on AssetHub.
-            let rpc_client = RpcClient::connect(&ws).await?;
-            let at: Hash = rpc_client.get_block_hash(block).await?;
-            eprintln!("Using AssetHub block hash 0x{}", hex::encode(at));
+        Commands::FetchSnapshot {
+            block,
+            out,
+            page_timeout_secs,
+        } => {
+            // Resolve block number (or finalized head) → hash on AssetHub.
+            let rpc_client = Arc::new(connect_rpc(&ws, cli.http).await?);
+            let at: Hash = rpc_client.resolve_block(block).await?;
+
+            // Raw storage keys below assume known pallet layouts; flag runtime upgrades.
+            match outdated_pallet_versions(&rpc_client, at).await {
+                Ok(outdated) => {
+                    for (pallet, on_chain, supported) in outdated {
+                        eprintln!(
+                            "[warn] {} storage version {} is newer than supported {}; \
+                             raw storage reads may use outdated keys",
+                            pallet, on_chain, supported
+                        );
+                    }
+                }
+                Err(e) => eprintln!("[warn] could not read pallet storage versions: {e:#}"),
+            }
 
             // Determine planning era at the snapshot block on AssetHub.
             if let Some(block_number) = block {
                 let planning_era = planning_era_at_ah_block(&rpc_client, block_number).await?;
-                println!(
-                    "[info] AH block {} has planning era (CurrentEra) = {}",
-                    block_number, planning_era
+                let active_era = active_era_at_ah_block(&rpc_client, block_number).await?;
+                eprintln!(
+                    "[info] AH block {} has planning era (CurrentEra) = {}, ActiveEra = {}",
+                    block_number, planning_era, active_era
                 );
             } else {
-                println!("[info] AH block is best block; planning era not resolved by number");
+                eprintln!("[info] AH block is best block; planning era not resolved by number");
             }
 
             // Use the Subxt-based multi-block source to pull the snapshot at that hash.
             let source = AhMultiBlockSource::connect(&ws).await?;
-            let snapshot = source.snapshot_at(at, MAX_PAGES).await?;
+            match source.check_snapshot_availability(at).await? {
+                SnapshotAvailability::Available { round } => {
+                    eprintln!("[info] snapshot for round {} available", round);
+                }
+                SnapshotAvailability::PalletUninitialized => {
+                    return Err(anyhow::anyhow!(
+                        "MultiBlockElection is not initialized at 0x{} (no Round / CurrentPhase)",
+                        hex::encode(at)
+                    ));
+                }
+                SnapshotAvailability::WrongPhase { phase } => {
+                    return Err(anyhow::anyhow!(
+                        "no complete snapshot at 0x{}: election phase is {:?}",
+                        hex::encode(at),
+                        phase
+                    ));
+                }
+                SnapshotAvailability::EmptySnapshot => {
+                    return Err(anyhow::anyhow!(
+                        "no target snapshot stored for the current round at 0x{}",
+                        hex::encode(at)
+                    ));
+                }
+            }
+            let progress = snapshot_progress_bar(max_pages);
+            let snapshot = source
+                .snapshot_at(
+                    at,
+                    max_pages,
+                    &progress,
+                    Duration::from_secs(page_timeout_secs),
+                )
+                .await?;
+            run_report.add_snapshot(&snapshot);
+            progress.finish_and_clear();
+            eprintln!("[info] fetched snapshot {}", snapshot.snapshot_id());
+
+            // Write first: the checks below are diagnostics and must not lose the snapshot.
+            snapshot_output(&snapshot, &out)?;
+
+            // Cross-check the snapshot's total issuance against a raw storage read.
+            match total_issuance_at(&rpc_client, at).await {
+                Ok(Some(issuance)) if issuance != snapshot.total_issuance => eprintln!(
+                    "[warn] Balances::TotalIssuance={} differs from snapshot total_issuance={}",
+                    issuance, snapshot.total_issuance
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("[warn] could not read Balances::TotalIssuance: {e:#}"),
+            }
 
-            // Serialize snapshot to JSON.
-            let json = snapshot_to_json(&snapshot)?;
-            fs::write(&out, json)?;
-            eprintln!("Snapshot written to {}", out.display());
+            // Every target should still be a registered validator; stale targets have
+            // chilled or fallen below `MinValidatorBond` since the snapshot was taken.
+            match unregistered_validators_at(
+                rpc_client.clone(),
+                at,
+                &snapshot.all_targets,
+                cli.max_concurrent_rpc,
+            )
+            .await
+            {
+                Ok(unregistered) => {
+                    for target in &unregistered {
+                        eprintln!(
+                            "[warn] target {} has no Staking::Validators entry",
+                            account_formatter().fmt(target)
+                        );
+                    }
+                    eprintln!(
+                        "[info] {}/{} targets are registered validators",
+                        snapshot.all_targets.len() - unregistered.len(),
+                        snapshot.all_targets.len()
+                    );
+                }
+                Err(e) => eprintln!("[warn] could not check target registration: {e:#}"),
+            }
+        }
+
+        Commands::WaitForSnapshot => {
+            let ah_rpc = connect_rpc(&ws, cli.http).await?;
+            let source = AhMultiBlockSource::connect(&ws).await?;
+            let mut heads = std::pin::pin!(ah_rpc.subscribe_finalized_heads().await?);
+            while let Some(head) = heads.next().await {
+                let head = head?;
+                match source.check_snapshot_availability(head.hash).await? {
+                    SnapshotAvailability::Available { round } => {
+                        println!(
+                            "snapshot for round {} available at finalized block {} (0x{})",
+                            round,
+                            head.number,
+                            hex::encode(head.hash)
+                        );
+                        return Ok(());
+                    }
+                    other => eprintln!("[info] block {}: {:?}", head.number, other),
+                }
+            }
+            return Err(anyhow::anyhow!("finalized heads subscription ended"));
         }
 
         Commands::RunOffline {
             input,
             compare_block,
+            queued_block,
+            boundary_neighborhood,
+            count_only,
+            no_extended_ranking,
+            compare_queued,
             debug_exposures,
             exposure_block,
             exposure_era,
+            strict_exposures,
+            exposure_tolerance,
             reduce,
+            pre_reduce,
+            normalize_weights,
+            max_targets_per_voter,
+            merge_pages,
+            exclude_validators,
+            trim_targets,
+            desired_source,
+            desired_targets,
+            sensitivity,
+            assert_min_support,
+            assert_score,
+            cross_check,
+            max_election_iterations,
+            ratio_assignments_out,
+            require_complete_solution,
+            targets_out,
+            filter_zero_support,
+            keep_zero_weight_backers,
+            report_unassigned,
+            check_balancing,
+            no_cache,
+            winners_out,
+            winners_list,
+            format,
+            summary_out,
+            report_md,
+            find_validators_for,
+            skip_checksum,
+            skip_reduce_verify,
+            timing,
         } => {
+            let started = Instant::now();
+            set_election_cache_enabled(!no_cache);
+            // With `--format jsonl`, stdout carries only the winner lines.
+            set_report_to_stderr(matches!(format, WinnersFormat::Jsonl));
+            set_reduce_verify_enabled(!skip_reduce_verify);
+
             // Load snapshot from JSON.
             let data = fs::read_to_string(&input)?;
-            let snapshot = snapshot_from_json(&data)?;
+            let mut snapshot = if skip_checksum {
+                snapshot_from_json_unverified(&data)?
+            } else {
+                snapshot_from_json(&data)?
+            };
+            let snapshot_load_ms = started.elapsed().as_millis();
+            eprintln!("[info] loaded snapshot {}", snapshot.snapshot_id());
+            run_report.add_snapshot(&snapshot);
+
+            if snapshot.has_empty_pages() {
+                let non_empty = snapshot.drop_empty_pages().voter_pages.len();
+                eprintln!(
+                    "[info] {} of {} voter pages are empty",
+                    snapshot.voter_pages.len() - non_empty,
+                    snapshot.voter_pages.len()
+                );
+            }
+
+            if merge_pages {
+                let pages_before = snapshot.voter_pages.len();
+                snapshot = snapshot.merge_voter_pages();
+                eprintln!(
+                    "[info] merged {} voter pages into 1 page with {} voters",
+                    pages_before,
+                    snapshot.voter_pages[0].len()
+                );
+            }
+
+            if let Some(path) = &exclude_validators {
+                let excluded = read_account_list(path)?;
+                let shortened = snapshot
+                    .voter_pages
+                    .iter()
+                    .flatten()
+                    .filter(|v| v.targets.iter().any(|t| excluded.contains(t)))
+                    .count();
+                let targets_before = snapshot.all_targets.len();
+                snapshot = snapshot.exclude_targets(&excluded);
+                eprintln!(
+                    "[info] excluded {} of {} listed validators from targets; {} voters had their target lists shortened",
+                    targets_before - snapshot.all_targets.len(),
+                    excluded.len(),
+                    shortened
+                );
+            }
+
+            if let Some(n) = trim_targets {
+                let targets_before = snapshot.all_targets.len();
+                snapshot = snapshot.trim_targets_to_top_n_by_nominating_weight(n);
+                eprintln!(
+                    "[info] trimmed targets to the top {} by nominating weight ({} -> {})",
+                    n,
+                    targets_before,
+                    snapshot.all_targets.len()
+                );
+            }
+
+            if desired_targets.is_some() && !matches!(desired_source, DesiredSource::Override) {
+                return Err(anyhow::anyhow!(
+                    "--desired-targets is only used with --desired-source override"
+                ));
+            }
+            let stored_desired = snapshot.desired_targets;
+            match desired_source {
+                DesiredSource::Snapshot => {}
+                DesiredSource::ValidatorCount => {
+                    let ah_rpc = connect_rpc(&ws, cli.http).await?;
+                    snapshot.desired_targets = validator_count_at(&ah_rpc, snapshot.at)
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Staking::ValidatorCount not found at snapshot block 0x{}",
+                                hex::encode(snapshot.at)
+                            )
+                        })?;
+                }
+                DesiredSource::Override => {
+                    snapshot.desired_targets = desired_targets.ok_or_else(|| {
+                        anyhow::anyhow!("--desired-source override requires --desired-targets <n>")
+                    })?;
+                }
+            }
+            eprintln!(
+                "[info] desired_targets={} (source={:?}, snapshot stored {})",
+                snapshot.desired_targets, desired_source, stored_desired
+            );
 
             // Run offline election with stake pipeline, controlled by `--reduce`.
-            let outputs = run_offline_election_with_stake(&snapshot, reduce)?;
-            let res = &outputs.raw;
-            let winners = staked_assignments_to_offline_winners(&outputs);
+            let election_started = Instant::now();
+            let outputs = run_offline_election_with_stake(
+                &snapshot,
+                reduce,
+                pre_reduce,
+                max_election_iterations,
+                normalize_weights,
+                max_targets_per_voter,
+            )?;
+            let election_ms = election_started.elapsed().as_millis();
+            eprintln!(
+                "[timing] election computation {} ms (algorithm + reduce), {} ms with input preparation",
+                outputs.computation_time_ms, election_ms
+            );
+            if outputs.saturated_shares > 0 {
+                eprintln!(
+                    "[warn] {} staked shares exceed u64::MAX and are clamped in the winner list",
+                    outputs.saturated_shares
+                );
+            }
+            // Outputs of a normalized run are checked against normalized weights.
+            let weights_snapshot = if normalize_weights {
+                normalize_snapshot_weights(&snapshot)
+            } else {
+                snapshot.clone()
+            };
+            let winners = staked_assignments_to_offline_winners(
+                &outputs,
+                filter_zero_support,
+                keep_zero_weight_backers,
+            );
+            run_report.winners = winners.len();
+
+            match format {
+                WinnersFormat::Text => {
+                    eprintln!("Offline winners ({}):", winners.len());
+                    for (i, w) in winners.iter().enumerate() {
+                        eprintln!(
+                            "#{:<4} validator={} support={} backers={}",
+                            i + 1,
+                            account_formatter().fmt(&w.validator),
+                            w.support,
+                            w.backers.len()
+                        );
+                    }
+                }
+                WinnersFormat::Jsonl => write_winners_jsonl(&winners, std::io::stdout().lock())?,
+            }
+
+            if !outputs.is_complete(snapshot.desired_targets) {
+                eprintln!(
+                    "[warn] incomplete election: {} winners for desired_targets={}",
+                    outputs.raw.winners.len(),
+                    snapshot.desired_targets
+                );
+                if require_complete_solution {
+                    run_report.print("exit 2");
+                    std::process::exit(2);
+                }
+            }
 
-            eprintln!("Offline winners ({}):", winners.len());
-            for (i, w) in winners.iter().enumerate() {
+            if let Some(path) = &ratio_assignments_out {
+                let ratios = ratio_assignments_for_inspection(&outputs);
+                fs::write(path, serde_json::to_string_pretty(&ratios)?)?;
                 eprintln!(
-                    "#{:<4} validator=0x{} support={} backers={}",
-                    i,
-                    hex::encode(w.validator),
-                    w.support,
-                    w.backers.len()
+                    "Ratio assignments ({} voters) written to {}",
+                    ratios.len(),
+                    path.display()
                 );
             }
 
-            if let Err(e) = verify_staked_assignments_internal(&snapshot, &outputs) {
+            if let Some(path) = &targets_out {
+                let targets = target_approvals(&snapshot, &outputs);
+                let is_csv = path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+                if is_csv {
+                    let mut csv = String::from("rank,target,approval_stake,elected\n");
+                    for (i, t) in targets.iter().enumerate() {
+                        csv.push_str(&format!(
+                            "{},{},{},{}\n",
+                            i,
+                            account_formatter().fmt(&t.target),
+                            t.approval_stake,
+                            t.elected
+                        ));
+                    }
+                    fs::write(path, csv)?;
+                } else {
+                    fs::write(path, serde_json::to_string_pretty(&targets)?)?;
+                }
+                eprintln!("Targets ({}) written to {}", targets.len(), path.display());
+            }
+
+            if let Some(path) = &winners_out {
+                fs::write(path, serde_json::to_string_pretty(&winners)?)?;
+                eprintln!("Winners ({}) written to {}", winners.len(), path.display());
+            }
+
+            if let Some(path) = &winners_list {
+                let formatter = AccountFormatter {
+                    ss58_prefix: Some(cli.ss58_prefix.unwrap_or(DEFAULT_WINNERS_LIST_SS58_PREFIX)),
+                };
+                fs::write(path, winners_address_list(&winners, &formatter))?;
+                eprintln!(
+                    "Winner addresses ({}) written to {}",
+                    winners.len(),
+                    path.display()
+                );
+            }
+
+            let verification_started = Instant::now();
+            if let Err(e) = verify_staked_assignments_internal(&weights_snapshot, &outputs) {
                 eprintln!("WARNING: internal stake verification failed: {e:?}");
             }
+            let verification_ms = verification_started.elapsed().as_millis();
+
+            if report_unassigned {
+                let unassigned = find_unassigned_voters(&weights_snapshot, &outputs);
+                let weight: u128 = unassigned.iter().map(|(_, w)| *w as u128).sum();
+                report!(
+                    "Unassigned voters: {} with total weight {} ({})",
+                    unassigned.len(),
+                    weight,
+                    balance_formatter().format(weight)
+                );
+                for (who, w) in unassigned.iter().take(10) {
+                    report!(
+                        "  {} weight={} ({})",
+                        account_formatter().fmt(who),
+                        w,
+                        balance_formatter().format(*w as u128)
+                    );
+                }
+            }
+
+            if let Some(tolerance) = check_balancing {
+                let violations = find_balancing_violations(&weights_snapshot, &outputs, tolerance)?;
+                if violations.is_empty() {
+                    eprintln!(
+                        "[balancing] no voter can shift more than {} between two of its winners",
+                        tolerance
+                    );
+                } else {
+                    eprintln!(
+                        "[warn] balancing: {} voters can shift more than {} between two of their winners (largest first):",
+                        violations.len(),
+                        tolerance
+                    );
+                    for v in violations.iter().take(10) {
+                        eprintln!(
+                            "  {} from={} to={} shift={} ({})",
+                            account_formatter().fmt(&v.voter),
+                            account_formatter().fmt(&v.from),
+                            account_formatter().fmt(&v.to),
+                            v.shift,
+                            balance_formatter().format(v.shift)
+                        );
+                    }
+                }
+            }
+
+            let score = election_score(&outputs);
+            eprintln!(
+                "[score] minimal={} sum={} sum_sq={}",
+                score.minimal_stake, score.sum_stake, score.sum_stake_squared
+            );
+            if let Some(removed) = outputs.reduced_edges {
+                eprintln!("[reduce] removed {} edges; supports preserved", removed);
+            }
+
+            // A failed floor is returned only after the summary and report are written.
+            let floors_given = assert_min_support.is_some() || assert_score.is_some();
+            let floor_check = check_score_floors(&score, assert_min_support, assert_score.as_ref());
+            if let Err(e) = &floor_check {
+                eprintln!("[error] {e}");
+            }
+
+            // Optional: winner set stability across reduce and algorithm variants.
+            if cross_check {
+                let report = cross_check_winners(&snapshot, max_election_iterations)?;
+                for (variant, winners) in &report.variants {
+                    eprintln!("[cross-check] {}: {} winners", variant, winners.len());
+                }
+                if !report.divergent.is_empty() {
+                    for v in &report.divergent {
+                        let elected_by: Vec<&str> = report
+                            .variants
+                            .iter()
+                            .filter(|(_, w)| w.contains(v))
+                            .map(|(variant, _)| variant.as_str())
+                            .collect();
+                        eprintln!(
+                            "  DIVERGENT {} elected by [{}]",
+                            account_formatter().fmt(v),
+                            elected_by.join(", ")
+                        );
+                    }
+                    return Err(anyhow::anyhow!(
+                        "cross-check failed: {} validators are not elected by every variant",
+                        report.divergent.len()
+                    ));
+                }
+                eprintln!("[cross-check] winner sets identical across all variants");
+            }
+
+            // Optional: sensitivity of the winner set to voter weight perturbation.
+            if let Some(pct) = sensitivity {
+                eprintln!("[info] RNG seed = {} (pass --seed to reproduce)", cli.seed);
+                let mut rng = StdRng::seed_from_u64(cli.seed);
+                let report = run_sensitivity_analysis(
+                    &snapshot,
+                    reduce,
+                    pre_reduce,
+                    max_election_iterations,
+                    normalize_weights,
+                    max_targets_per_voter,
+                    pct,
+                    SENSITIVITY_ITERATIONS,
+                    &mut rng,
+                )?;
+
+                report!(
+                    "Sensitivity (±{}% voter weight, {} runs): stable={} boundary={} never={}",
+                    pct,
+                    report.iterations,
+                    report.stable.len(),
+                    report.boundary.len(),
+                    report.never.len(),
+                );
+                for (validator, count) in &report.boundary {
+                    report!(
+                        "  BOUNDARY {} elected in {}/{} runs",
+                        account_formatter().fmt(validator),
+                        count,
+                        report.iterations,
+                    );
+                }
+            }
 
             // Optional: debug exposures and nominator distributions.
+            let mut exposure_summary: Option<ExposureSummary> = None;
+            let mut exposure_build_ms = 0;
+            let mut exposure_fetch_ms = 0;
             if debug_exposures {
                 let exposure_block = match exposure_block {
                     Some(b) => b,
@@ -204,31 +1197,31 @@ async fn main() -> Result<()> {
                 };
 
                 // Build global snapshot voter set for debugging.
-                let mut all_snapshot_voters: BTreeSet<AccountId> = BTreeSet::new();
-                for page in &snapshot.voter_pages {
-                    for v in page {
-                        all_snapshot_voters.insert(v.who);
-                    }
-                }
+                let all_snapshot_voters = snapshot.voter_set();
 
                 // Build runtime-like exposures (per validator: total, own, nominators)
                 // in `Balance` units, using the same pipeline as on-chain.
-                let offline_exposures = build_runtime_exposures_from_staked(&snapshot, &outputs);
+                let exposure_build_started = Instant::now();
+                let offline_exposures = build_runtime_exposures_from_staked(&outputs);
+                exposure_build_ms = exposure_build_started.elapsed().as_millis();
 
                 // Connect a Subxt client to AssetHub.
+                let exposure_fetch_started = Instant::now();
                 let ah_client = OnlineClient::<PolkadotConfig>::from_url(&ws).await?;
 
                 // Resolve exposure block number to block hash.
-                let ah_rpc = RpcClient::connect(&ws).await?;
+                let ah_rpc = connect_rpc(&ws, cli.http).await?;
                 let at_ah: Hash = ah_rpc.get_block_hash(Some(exposure_block)).await?;
 
                 // Fetch `CurrentEra` and `ActiveEra` at the exposure block, for information.
                 let current_era_on_chain = fetch_current_era_at(&ah_client, at_ah).await?;
                 let active_era_on_chain = fetch_active_era_at(&ah_client, at_ah).await?;
 
-                println!(
+                report!(
                     "[info] on-chain CurrentEra={} ActiveEra={} at exposure block (user-requested era={})",
-                    current_era_on_chain, active_era_on_chain, exposure_era,
+                    current_era_on_chain,
+                    active_era_on_chain,
+                    exposure_era,
                 );
 
                 eprintln!(
@@ -242,25 +1235,71 @@ async fn main() -> Result<()> {
 
                 // Fetch paged exposures and overview metadata from on-chain
                 // for the user-specified era.
-                let onchain_pages = fetch_onchain_exposures_for_era(
-                    &ah_client,
-                    at_ah,
+                // Enumerate every validator exposed on-chain in that era, independently
+                // of the offline winners.
+                let onchain_overviews =
+                    fetch_all_overviews(&ah_client, &ah_rpc, at_ah, exposure_era).await?;
+
+                let offline_validator_set: BTreeSet<AccountId> =
+                    offline_validators.iter().copied().collect();
+                let onchain_only_exposed: Vec<&AccountId> = onchain_overviews
+                    .keys()
+                    .filter(|v| !offline_validator_set.contains(*v))
+                    .collect();
+                eprintln!(
+                    "[info] {} validators exposed on-chain in era {}, {} of them not offline winners",
+                    onchain_overviews.len(),
                     exposure_era,
-                    &offline_validators,
-                    MAX_PAGES,
-                )
-                .await?;
+                    onchain_only_exposed.len(),
+                );
+                for v in onchain_only_exposed.iter().take(10) {
+                    eprintln!("    ON-CHAIN-EXPOSED-ONLY {}", account_formatter().fmt(v));
+                }
 
-                let onchain_overviews = fetch_overviews_for_validators(
+                let onchain_pages = fetch_onchain_exposures_for_era(
                     &ah_client,
                     at_ah,
                     exposure_era,
                     &offline_validators,
+                    &onchain_overviews,
+                    max_pages,
+                    cli.max_concurrent_rpc,
                 )
                 .await?;
+                exposure_fetch_ms = exposure_fetch_started.elapsed().as_millis();
 
                 // Flatten paged on-chain exposures into `validator -> {nominator -> stake}`.
-                let onchain_flat = flatten_onchain_backers(&onchain_pages);
+                let (onchain_flat, duplicates) = flatten_onchain_backers(&onchain_pages);
+                for d in &duplicates {
+                    eprintln!(
+                        "[warn] nominator {} listed twice for validator {} (pages {} and {}); stakes summed",
+                        account_formatter().fmt(&d.nominator),
+                        account_formatter().fmt(&d.validator),
+                        d.page_a,
+                        d.page_b,
+                    );
+                }
+
+                let total_errors =
+                    verify_onchain_exposure_totals(&onchain_flat, &onchain_overviews);
+                eprintln!(
+                    "[info] {} validators with non-zero flattened vs overview total delta",
+                    total_errors.len()
+                );
+                for e in total_errors
+                    .iter()
+                    .filter(|e| e.delta.unsigned_abs() > exposure_tolerance)
+                {
+                    eprintln!(
+                        "    TOTAL-MISMATCH {} flat_sum={} ({}) overview_total={} ({}) delta={:+}",
+                        account_formatter().fmt(&e.validator),
+                        e.flat_sum,
+                        balance_formatter().format(e.flat_sum),
+                        e.overview_total,
+                        balance_formatter().format(e.overview_total),
+                        e.delta,
+                    );
+                }
 
                 let offline_nom_view = build_offline_nom_view(&offline_exposures);
                 let onchain_nom_view = build_onchain_nom_view(&onchain_flat);
@@ -269,10 +1308,11 @@ async fn main() -> Result<()> {
                 let mut matched_nominator_sets = 0usize;
                 let mut mismatched_nominator_sets = 0usize;
 
-                // Limit how many validators are debugged in detail.
+                // Limit how many validators are debugged in detail; largest
+                // exposures first so the detailed ones are the most relevant.
                 let mut debug_mismatches_left = 5usize;
 
-                for (validator, off_exp) in &offline_exposures {
+                for (validator, off_exp) in offline_exposures.iter_sorted_by_support() {
                     // Offline nominators for this validator (set of AccountId).
                     let off_nom_set: BTreeSet<AccountId> =
                         off_exp.others.iter().map(|b| b.who).collect();
@@ -290,8 +1330,8 @@ async fn main() -> Result<()> {
                         Some(ov) => ov,
                         None => {
                             eprintln!(
-                                "[warn] No on-chain ErasStakersOverview for validator 0x{} in era {}",
-                                hex::encode(validator),
+                                "[warn] No on-chain ErasStakersOverview for validator {} in era {}",
+                                account_formatter().fmt(validator),
                                 exposure_era,
                             );
                             mismatched_nominator_sets += 1;
@@ -304,10 +1344,24 @@ async fn main() -> Result<()> {
 
                     // Check that the number of nominators matches the on-chain metadata.
                     if on_count as u32 != on_overview.nominator_count {
+                        let fetched_pages = onchain_pages.get(validator).map_or(0, |p| p.len());
+                        if strict_exposures {
+                            return Err(anyhow::anyhow!(
+                                "Validator {}: on-chain nominator_count={} (page_count={}) but \
+                                 flattened {} pages have {} nominators; paged exposure fetch is incomplete",
+                                account_formatter().fmt(validator),
+                                on_overview.nominator_count,
+                                on_overview.page_count,
+                                fetched_pages,
+                                on_count,
+                            ));
+                        }
                         eprintln!(
-                            "[warn] Validator 0x{}: on-chain nominator_count={} but flattened pages have {} nominators",
-                            hex::encode(validator),
+                            "[warn] Validator {}: on-chain nominator_count={} (page_count={}) but flattened {} pages have {} nominators",
+                            account_formatter().fmt(validator),
                             on_overview.nominator_count,
+                            on_overview.page_count,
+                            fetched_pages,
                             on_count,
                         );
                     }
@@ -324,8 +1378,8 @@ async fn main() -> Result<()> {
                             on_nom_set.difference(&off_nom_set).copied().collect();
 
                         eprintln!(
-                            "[mismatch] Validator 0x{}: nominator sets differ. only_offline={} only_onchain={}",
-                            hex::encode(validator),
+                            "[mismatch] Validator {}: nominator sets differ. only_offline={} only_onchain={}",
+                            account_formatter().fmt(validator),
                             only_offline.len(),
                             only_onchain.len(),
                         );
@@ -356,7 +1410,7 @@ async fn main() -> Result<()> {
                                     "    nominators only in OFFLINE assignment for this validator (first 5):"
                                 );
                                 for who in only_offline.iter().take(5) {
-                                    eprintln!("      OFF  0x{}", hex::encode(who));
+                                    eprintln!("      OFF  {}", account_formatter().fmt(who));
                                 }
                             }
 
@@ -366,7 +1420,7 @@ async fn main() -> Result<()> {
                                     "    nominators only in ON-CHAIN exposure for this validator (first 5):"
                                 );
                                 for who in only_onchain.iter().take(5) {
-                                    eprintln!("      ON   0x{}", hex::encode(who));
+                                    eprintln!("      ON   {}", account_formatter().fmt(who));
                                 }
                             }
 
@@ -395,10 +1449,12 @@ async fn main() -> Result<()> {
                                             crate::types::balance_to_vote_weight(on_stake);
 
                                         eprintln!(
-                                            "      0x{}: off_stake={} on_stake={} off_vote={} on_vote={}",
-                                            hex::encode(who),
+                                            "      {}: off_stake={} ({}) on_stake={} ({}) off_vote={} on_vote={}",
+                                            account_formatter().fmt(who),
                                             off_stake,
+                                            balance_formatter().format(off_stake),
                                             on_stake,
+                                            balance_formatter().format(on_stake),
                                             off_vote,
                                             on_vote,
                                         );
@@ -414,12 +1470,34 @@ async fn main() -> Result<()> {
                             // Pick a few nominators from each side for detailed debugging.
                             for who in only_onchain.iter().take(2) {
                                 eprintln!("  --- DEBUG nominator only_onchain ---");
-                                debug_nominator(who, &offline_nom_view, &onchain_nom_view);
+                                // Read at the snapshot block, where the voter weight was taken.
+                                let ledger = staking_ledger_at(&ah_rpc, snapshot.at, who).await;
+                                debug_nominator(
+                                    who,
+                                    &offline_nom_view,
+                                    &onchain_nom_view,
+                                    Some(&snapshot),
+                                    &ledger,
+                                );
+                                if let Err(e) = print_account_info(&ah_rpc, who, at_ah).await {
+                                    eprintln!("[warn] account info unavailable: {e:#}");
+                                }
                             }
 
                             for who in only_offline.iter().take(2) {
                                 eprintln!("  --- DEBUG nominator only_offline ---");
-                                debug_nominator(who, &offline_nom_view, &onchain_nom_view);
+                                // Read at the snapshot block, where the voter weight was taken.
+                                let ledger = staking_ledger_at(&ah_rpc, snapshot.at, who).await;
+                                debug_nominator(
+                                    who,
+                                    &offline_nom_view,
+                                    &onchain_nom_view,
+                                    Some(&snapshot),
+                                    &ledger,
+                                );
+                                if let Err(e) = print_account_info(&ah_rpc, who, at_ah).await {
+                                    eprintln!("[warn] account info unavailable: {e:#}");
+                                }
                             }
                         }
                     }
@@ -437,17 +1515,21 @@ async fn main() -> Result<()> {
                     let on_own_as_vote = crate::types::balance_to_vote_weight(on_own);
 
                     eprintln!(
-                        "[exposure] validator=0x{} \
-                        off_total={} off_own={} \
-                        on_total={} on_own={} \
+                        "[exposure] validator={} \
+                        off_total={} ({}) off_own={} ({}) \
+                        on_total={} ({}) on_own={} ({}) \
                         off_total_as_vote={} off_own_as_vote={} \
                         on_total_as_vote={} on_own_as_vote={} \
                         nominators_offline={} nominators_onchain={}",
-                        hex::encode(validator),
+                        account_formatter().fmt(validator),
                         off_total,
+                        balance_formatter().format(off_total),
                         off_own,
+                        balance_formatter().format(off_own),
                         on_total,
+                        balance_formatter().format(on_total),
                         on_own,
+                        balance_formatter().format(on_own),
                         off_total_as_vote,
                         off_own_as_vote,
                         on_total_as_vote,
@@ -461,33 +1543,893 @@ async fn main() -> Result<()> {
                     "[summary] exposure comparison vs AssetHub era {}: matched_nominator_sets={} mismatched_nominator_sets={}",
                     exposure_era, matched_nominator_sets, mismatched_nominator_sets,
                 );
+                let offline_total_stake = offline_exposures.total_stake();
+                eprintln!(
+                    "[summary] offline exposures: total_stake={} ({}) own_stake_fraction={:.4}",
+                    offline_total_stake,
+                    balance_formatter().format(offline_total_stake),
+                    offline_exposures.own_stake_fraction(),
+                );
+                exposure_summary = Some(ExposureSummary {
+                    era: exposure_era,
+                    matched_nominator_sets,
+                    mismatched_nominator_sets,
+                });
             }
 
-            // Optional: compare with relay `Session::Validators` at a given block.
+            // Optional: compare with the verifier's queued solution on AssetHub.
+            let offline_ids: Vec<AccountId> = winners.iter().map(|w| w.validator).collect();
+            let mut queued_ids: Option<Vec<AccountId>> = None;
+            if let Some(block) = queued_block {
+                let ah_rpc = connect_rpc(&ws, cli.http).await?;
+                let at_ah: Hash = ah_rpc.get_block_hash(Some(block)).await?;
+                let ah_client = OnlineClient::<PolkadotConfig>::from_url(&ws).await?;
+
+                match fetch_queued_winners(&ah_client, at_ah, snapshot.round, max_pages).await? {
+                    Some(queued) => {
+                        let ids: Vec<AccountId> = queued.iter().map(|w| w.validator).collect();
+                        warn_winner_count_mismatch(
+                            "queued",
+                            offline_ids.len(),
+                            snapshot.desired_targets,
+                            ids.len(),
+                        );
+                        let (common, only_offline, only_queued) =
+                            compare_winners_with_chain(&offline_ids, &ids);
+                        report!(
+                            "Comparison with queued solution (round {}, AH block {}): \
+                             match={}, only_offline={}, only_queued={}",
+                            snapshot.round,
+                            block,
+                            common,
+                            only_offline,
+                            only_queued,
+                        );
+                        queued_ids = Some(ids);
+                    }
+                    None => eprintln!(
+                        "[warn] no queued solution for round {} at AH block {}",
+                        snapshot.round, block
+                    ),
+                }
+            }
+
+            // Optional: compare with relay `Session::Validators` (or `Session::QueuedKeys`)
+            // at a given block.
+            let mut relay_summary: Option<RelaySummary> = None;
+            let mut relay_set: Option<BTreeSet<AccountId>> = None;
+            let relay_compare_started = Instant::now();
             if let Some(block) = compare_block {
                 if let Some(relay_ws) = &relay_ws {
-                    let relay_client = RpcClient::connect(relay_ws).await?;
+                    let relay_client = connect_rpc(relay_ws, cli.http).await?;
                     let at_relay: Hash = relay_client.get_block_hash(Some(block)).await?;
 
-                    let onchain = fetch_relay_session_validators(&relay_client, at_relay).await?;
+                    let (onchain, source) = if compare_queued {
+                        if let Some(changed) =
+                            session_queued_changed_at(&relay_client, at_relay).await?
+                        {
+                            eprintln!(
+                                "[info] Session::QueuedChanged={} at relay block {}",
+                                changed, block
+                            );
+                        }
+                        (
+                            fetch_relay_queued_validators(&relay_client, at_relay).await?,
+                            "Session::QueuedKeys",
+                        )
+                    } else {
+                        (
+                            fetch_relay_session_validators(&relay_client, at_relay).await?,
+                            "Session::Validators",
+                        )
+                    };
                     eprintln!(
-                        "On-chain RELAY Session::Validators at block {}: {} entries",
+                        "On-chain RELAY {} at block {}: {} entries",
+                        source,
                         block,
                         onchain.len()
                     );
+                    let onchain_set: BTreeSet<AccountId> = onchain.iter().copied().collect();
+                    warn_winner_count_mismatch(
+                        source,
+                        offline_ids.len(),
+                        snapshot.desired_targets,
+                        onchain.len(),
+                    );
 
-                    // Detailed diff and boundary debugging.
-                    compare_with_relay(&snapshot, &res, &onchain);
-                    debug_boundary_ranks(&winners, &onchain);
+                    if let Some(queued) = &queued_ids {
+                        let (common, only_queued, only_relay) =
+                            compare_winners_with_chain(queued, &onchain);
+                        report!(
+                            "Comparison queued solution vs RELAY {}: \
+                             match={}, only_queued={}, only_relay={}",
+                            source,
+                            common,
+                            only_queued,
+                            only_relay,
+                        );
+                    }
+
+                    let (common, only_offline, only_onchain) =
+                        compare_winners_with_chain(&offline_ids, &onchain);
+                    relay_summary = Some(RelaySummary {
+                        block,
+                        source: source.to_string(),
+                        matched: common,
+                        only_offline,
+                        only_onchain,
+                    });
+
+                    if count_only {
+                        report!(
+                            "Comparison with RELAY {}: match={}, only_offline={}, only_onchain={}",
+                            source,
+                            common,
+                            only_offline,
+                            only_onchain,
+                        );
+                    } else {
+                        // Detailed diff and boundary debugging. Offline winners keep
+                        // the rank and support of this run.
+                        let offline_set: BTreeSet<AccountId> =
+                            offline_ids.iter().copied().collect();
+                        let mut rank_map: HashMap<AccountId, (usize, u128)> = winners
+                            .iter()
+                            .enumerate()
+                            .map(|(rank, w)| (w.validator, (rank, u128::from(w.support))))
+                            .collect();
+                        if only_onchain > 0 && !no_extended_ranking {
+                            let extended = extended_offline_ranking(
+                                &snapshot,
+                                only_onchain,
+                                reduce,
+                                pre_reduce,
+                                max_election_iterations,
+                                normalize_weights,
+                                max_targets_per_voter,
+                            )?;
+                            for v in onchain_set.difference(&offline_set) {
+                                if let Some(rank) = offline_rank_of(v, &extended) {
+                                    rank_map.insert(*v, (rank, u128::from(extended[rank].support)));
+                                }
+                            }
+                        }
+                        report!(
+                            "Comparison with RELAY {}: match={}, only_offline={}, \
+                             only_onchain={}, kendall_tau={:.3}",
+                            source,
+                            common,
+                            only_offline,
+                            only_onchain,
+                            kendall_tau(&offline_ids, &onchain),
+                        );
+                        let all_targets: BTreeSet<AccountId> =
+                            snapshot.all_targets.iter().copied().collect();
+                        compare_with_relay(
+                            &offline_set,
+                            &all_targets,
+                            &onchain_set,
+                            Some(&rank_map),
+                        );
+                        let boundary =
+                            debug_boundary_ranks(&winners, &onchain, boundary_neighborhood);
+                        print_boundary_report(&boundary);
+                    }
+                    relay_set = Some(onchain_set);
                 } else {
                     eprintln!(
                         "WARNING: --compare-block was given but --relay-ws/RELAY_WS is missing; \
-                         cannot compare against relay Session::Validators."
+                         cannot compare against the relay validator set."
+                    );
+                }
+            }
+            let relay_compare_ms = relay_compare_started.elapsed().as_millis();
+
+            if let Some(who) = &find_validators_for {
+                let nom_view =
+                    build_offline_nom_view(&build_runtime_exposures_from_staked(&outputs));
+                let backed = find_validators_for_nominator(who, &nom_view);
+
+                // Support rank: position among offline winners by descending support.
+                let mut by_support: Vec<&OfflineWinner> = winners.iter().collect();
+                by_support.sort_by_key(|w| std::cmp::Reverse(w.support));
+
+                eprintln!(
+                    "Validators backed by {} offline ({}):",
+                    account_formatter().fmt(who),
+                    backed.len()
+                );
+                for (validator, stake) in &backed {
+                    let rank = by_support.iter().position(|w| w.validator == *validator);
+                    let in_relay = match &relay_set {
+                        Some(set) => {
+                            if set.contains(validator) {
+                                "yes"
+                            } else {
+                                "no"
+                            }
+                        }
+                        None => "n/a",
+                    };
+                    eprintln!(
+                        "  -> validator {} stake={} ({}) winner={} support_rank={} in_relay={}",
+                        account_formatter().fmt(validator),
+                        stake,
+                        balance_formatter().format(*stake),
+                        rank.is_some(),
+                        rank.map_or_else(|| "-".to_string(), |r| format!("#{}", r)),
+                        in_relay,
+                    );
+                }
+            }
+
+            let summary = compute_election_summary(&weights_snapshot, &outputs);
+            eprintln!(
+                "[summary] winners={} total_voter_weight={} total_assigned_weight={} \
+                 assigned_fraction={:.4} total_support={} min_support={} max_support={}",
+                summary.winner_count,
+                summary.total_voter_weight,
+                summary.total_assigned_weight,
+                summary.assigned_fraction,
+                summary.total_support,
+                summary.min_support,
+                summary.max_support,
+            );
+            if summary.assigned_fraction < 0.99 {
+                eprintln!(
+                    "[warn] only {:.2}% of voter weight was assigned; some voters backed no winner",
+                    summary.assigned_fraction * 100.0
+                );
+            }
+
+            // Backer concentration: most and least dependent on a single nominator.
+            let mut by_concentration: Vec<&OfflineWinner> = winners.iter().collect();
+            by_concentration
+                .sort_by(|a, b| b.concentration_ratio().total_cmp(&a.concentration_ratio()));
+            let print_concentration = |label: &str, list: &[&OfflineWinner]| {
+                report!("{label}:");
+                for w in list {
+                    report!(
+                        "  {} top_backer={:.1}% hhi={:.3} backers={}",
+                        account_formatter().fmt(&w.validator),
+                        w.concentration_ratio() * 100.0,
+                        w.herfindahl_index(),
+                        w.backers.len()
                     );
                 }
+            };
+            let n = by_concentration.len().min(5);
+            print_concentration("Most concentrated backing", &by_concentration[..n]);
+            print_concentration(
+                "Least concentrated backing",
+                &by_concentration[by_concentration.len() - n..],
+            );
+
+            if summary_out.is_some() || report_md.is_some() {
+                let run_summary = RunSummary {
+                    snapshot: SnapshotSummary::from_snapshot(&snapshot),
+                    algorithm: outputs.algorithm.to_string(),
+                    reduce,
+                    pre_reduce,
+                    normalize_weights,
+                    desired_targets: snapshot.desired_targets,
+                    winner_count: winners.len(),
+                    assigned_fraction: summary.assigned_fraction,
+                    score: ScoreSummary::from(&score),
+                    min_support_floor: assert_min_support,
+                    score_floor: assert_score.as_ref().map(ScoreSummary::from),
+                    floors_met: floors_given.then_some(floor_check.is_ok()),
+                    relay: relay_summary,
+                    exposures: exposure_summary,
+                    timings_ms: timing.then(|| TimingsSummary {
+                        computation: outputs.computation_time_ms,
+                        election: election_ms,
+                        total: started.elapsed().as_millis(),
+                    }),
+                };
+                if let Some(path) = &summary_out {
+                    fs::write(path, serde_json::to_string_pretty(&run_summary)?)?;
+                    eprintln!("Run summary written to {}", path.display());
+                }
+                if let Some(path) = &report_md {
+                    let report = MarkdownReport {
+                        summary: &run_summary,
+                        winners: &winners,
+                    };
+                    fs::write(path, report.render())?;
+                    eprintln!("Markdown report written to {}", path.display());
+                }
             }
+
+            if timing {
+                let t = &outputs.timings;
+                let timings = RunTimings {
+                    snapshot_load_ms,
+                    flatten_voters_ms: t.flatten_voters_ms.into(),
+                    seq_phragmen_ms: t.elect_ms.into(),
+                    ratio_to_staked_ms: t.ratio_to_staked_ms.into(),
+                    reduce_ms: t.reduce_ms.into(),
+                    staked_to_ratio_ms: t.staked_to_ratio_ms.into(),
+                    verification_ms,
+                    exposure_build_ms,
+                    exposure_fetch_ms,
+                    relay_compare_ms,
+                    total_ms: started.elapsed().as_millis(),
+                };
+                eprint!("{}", timings.render_table());
+            }
+
+            floor_check?;
         }
+
+        Commands::VerifyWinners { snapshot, winners } => {
+            let snap = snapshot_from_json(&fs::read_to_string(&snapshot)?)?;
+            let winners: Vec<OfflineWinner> = serde_json::from_str(&fs::read_to_string(&winners)?)?;
+
+            run_report.add_snapshot(&snap);
+            run_report.winners = winners.len();
+            verify_winners_against_snapshot(&snap, &winners)?;
+
+            let score = winners_to_supports(&winners).evaluate();
+            println!(
+                "{} winners feasible for snapshot {} (desired={})",
+                winners.len(),
+                snap.snapshot_id(),
+                snap.desired_targets
+            );
+            println!(
+                "[score] minimal={} sum={} sum_sq={}",
+                score.minimal_stake, score.sum_stake, score.sum_stake_squared
+            );
+        }
+
+        Commands::CheckCandidates { input, reference } => {
+            let snapshot = snapshot_from_json(&fs::read_to_string(&input)?)?;
+            run_report.add_snapshot(&snapshot);
+            let reference_winners = read_account_list(&reference)?;
+
+            let missing: Vec<&AccountId> = reference_winners
+                .iter()
+                .filter(|who| !snapshot.is_target(who))
+                .collect();
+
+            println!(
+                "Candidate check for snapshot {}: reference={} targets={} missing={}",
+                snapshot.snapshot_id(),
+                reference_winners.len(),
+                snapshot.all_targets.len(),
+                missing.len()
+            );
+            for who in &missing {
+                println!("  MISSING {}", account_formatter().fmt(who));
+            }
+            if !missing.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{} reference winners are not candidates in the snapshot; it cannot reproduce {}",
+                    missing.len(),
+                    reference.display()
+                ));
+            }
+        }
+
+        Commands::CompareElections {
+            snapshot_a,
+            snapshot_b,
+            reduce,
+        } => {
+            let snap_a = snapshot_from_json(&fs::read_to_string(&snapshot_a)?)?;
+            let snap_b = snapshot_from_json(&fs::read_to_string(&snapshot_b)?)?;
+
+            let canonical_a = snap_a.canonicalize();
+            let canonical_b = snap_b.canonicalize();
+            if canonical_a.all_targets == canonical_b.all_targets
+                && canonical_a.voter_pages == canonical_b.voter_pages
+            {
+                println!("[info] A and B have identical targets and voter pages (up to ordering)");
+            }
+
+            run_report.add_snapshot(&snap_a);
+            run_report.add_snapshot(&snap_b);
+
+            let outputs_a =
+                run_offline_election_with_stake(&snap_a, reduce, false, None, false, None)?;
+            let outputs_b =
+                run_offline_election_with_stake(&snap_b, reduce, false, None, false, None)?;
+            run_report.winners = outputs_a.raw.winners.len() + outputs_b.raw.winners.len();
+
+            let cmp = compare_two_offline_elections(&snap_a, &outputs_a, &snap_b, &outputs_b);
+
+            println!(
+                "Comparison A={} ({}, desired={}) vs B={} ({}, desired={}): common={}, only_a={}, only_b={}",
+                snapshot_a.display(),
+                snap_a.snapshot_id(),
+                cmp.desired_a,
+                snapshot_b.display(),
+                snap_b.snapshot_id(),
+                cmp.desired_b,
+                cmp.common.len(),
+                cmp.only_a.len(),
+                cmp.only_b.len(),
+            );
+
+            if !cmp.common.is_empty() {
+                println!("\nValidators elected in BOTH (support A vs B):");
+                for c in &cmp.common {
+                    let delta = c.support_b as i128 - c.support_a as i128;
+                    println!(
+                        "  {} support_a={} support_b={} delta={:+}",
+                        account_formatter().fmt(&c.validator),
+                        c.support_a,
+                        c.support_b,
+                        delta,
+                    );
+                }
+            }
+
+            if !cmp.only_a.is_empty() {
+                println!("\nValidators only elected in A:");
+                for id in &cmp.only_a {
+                    println!("  {}", account_formatter().fmt(id));
+                }
+            }
+
+            if !cmp.only_b.is_empty() {
+                println!("\nValidators only elected in B:");
+                for id in &cmp.only_b {
+                    println!("  {}", account_formatter().fmt(id));
+                }
+            }
+        }
+
+        Commands::CompareExposures { a, b, reduce } => {
+            let snap_a = snapshot_from_json(&fs::read_to_string(&a)?)?;
+            let snap_b = snapshot_from_json(&fs::read_to_string(&b)?)?;
+
+            run_report.add_snapshot(&snap_a);
+            run_report.add_snapshot(&snap_b);
+
+            let outputs_a =
+                run_offline_election_with_stake(&snap_a, reduce, false, None, false, None)?;
+            let outputs_b =
+                run_offline_election_with_stake(&snap_b, reduce, false, None, false, None)?;
+            run_report.winners = outputs_a.raw.winners.len() + outputs_b.raw.winners.len();
+
+            let view_a = build_offline_nom_view(&build_runtime_exposures_from_staked(&outputs_a));
+            let view_b = build_offline_nom_view(&build_runtime_exposures_from_staked(&outputs_b));
+
+            let churn = exposure_churn(&view_a, &view_b);
+
+            let only_a = churn.iter().filter(|c| c.stake_b == 0).count();
+            let only_b = churn.iter().filter(|c| c.stake_a == 0).count();
+            println!(
+                "Exposure comparison A={} ({}) vs B={} ({}): validators={} only_a={} only_b={}",
+                a.display(),
+                snap_a.snapshot_id(),
+                b.display(),
+                snap_b.snapshot_id(),
+                churn.len(),
+                only_a,
+                only_b,
+            );
+
+            for c in &churn {
+                let delta = c.stake_b as i128 - c.stake_a as i128;
+                println!(
+                    "  {} backers stayed={} joined={} left={} stake_a={} stake_b={} delta={:+}",
+                    account_formatter().fmt(&c.validator),
+                    c.stayed,
+                    c.joined,
+                    c.left,
+                    c.stake_a,
+                    c.stake_b,
+                    delta,
+                );
+            }
+        }
+
+        Commands::TraceAccount {
+            input,
+            account,
+            block,
+        } => {
+            let snapshot = snapshot_from_json(&fs::read_to_string(&input)?)?;
+            run_report.add_snapshot(&snapshot);
+
+            let at: Hash = match block {
+                Some(n) => {
+                    connect_rpc(&ws, cli.http)
+                        .await?
+                        .get_block_hash(Some(n))
+                        .await?
+                }
+                None => snapshot.at,
+            };
+            let ah_client = OnlineClient::<PolkadotConfig>::from_url(&ws).await?;
+            let onchain = fetch_nominations_for_account(&ah_client, at, &account).await?;
+
+            println!(
+                "Account {} in snapshot {} vs on-chain at 0x{}",
+                account_formatter().fmt(&account),
+                snapshot.snapshot_id(),
+                hex::encode(at)
+            );
+
+            println!("  role in snapshot: {:?}", snapshot.role(&account));
+
+            let mut snapshot_targets: BTreeSet<AccountId> = BTreeSet::new();
+            for (_, weight, targets, page_idx) in flatten_voters_with_page_labels(&snapshot)
+                .into_iter()
+                .filter(|(who, ..)| *who == account)
+            {
+                println!(
+                    "  SNAPSHOT page {} weight={} targets={}",
+                    page_idx,
+                    weight,
+                    targets.len()
+                );
+                snapshot_targets.extend(targets);
+            }
+            if snapshot_targets.is_empty() {
+                println!("  SNAPSHOT: not a voter");
+            }
+
+            let Some(onchain) = onchain else {
+                println!("  ON-CHAIN: not a nominator");
+                return Ok(());
+            };
+            println!("  ON-CHAIN targets={}", onchain.len());
+
+            let onchain_targets: BTreeSet<AccountId> = onchain.iter().copied().collect();
+            for t in onchain_targets.difference(&snapshot_targets) {
+                println!(
+                    "    ONLY-ON-CHAIN {} (in snapshot.all_targets: {})",
+                    account_formatter().fmt(t),
+                    if snapshot.is_target(t) { "yes" } else { "NO" }
+                );
+            }
+            for t in snapshot_targets.difference(&onchain_targets) {
+                println!("    ONLY-SNAPSHOT {}", account_formatter().fmt(t));
+            }
+        }
+
+        Commands::ProjectRewards {
+            input,
+            era_reward,
+            block,
+            reduce,
+        } => {
+            let snapshot = snapshot_from_json(&fs::read_to_string(&input)?)?;
+            run_report.add_snapshot(&snapshot);
+
+            let outputs =
+                run_offline_election_with_stake(&snapshot, reduce, false, None, false, None)?;
+            run_report.winners = outputs.raw.winners.len();
+            let exposures = build_runtime_exposures_from_staked(&outputs);
+
+            let at: Hash = match block {
+                Some(n) => {
+                    connect_rpc(&ws, cli.http)
+                        .await?
+                        .get_block_hash(Some(n))
+                        .await?
+                }
+                None => snapshot.at,
+            };
+            let ah_client = OnlineClient::<PolkadotConfig>::from_url(&ws).await?;
+            let winners: Vec<AccountId> = exposures.keys().copied().collect();
+            let commissions =
+                fetch_validator_commissions(&ah_client, at, &winners, cli.max_concurrent_rpc)
+                    .await?;
+            let missing = winners.len() - commissions.len();
+            if missing > 0 {
+                eprintln!(
+                    "[warn] {} winners have no Staking::Validators prefs at 0x{}; assuming 0% commission",
+                    missing,
+                    hex::encode(at)
+                );
+            }
+
+            let projections = project_rewards(&exposures, &commissions, era_reward);
+
+            println!(
+                "Reward projection for {}: era_reward={} ({}) winners={} commission_at=0x{}",
+                snapshot.snapshot_id(),
+                era_reward,
+                balance_formatter().format(era_reward),
+                projections.len(),
+                hex::encode(at)
+            );
+            for p in &projections {
+                println!(
+                    "  {} support={} commission={:.2}% share={} validator_take={} ({}) to_nominators={} ({})",
+                    account_formatter().fmt(&p.validator),
+                    balance_formatter().format(p.support),
+                    p.commission.deconstruct() as f64 / 1e7,
+                    p.era_share,
+                    p.validator_take,
+                    balance_formatter().format(p.validator_take),
+                    p.to_nominators,
+                    balance_formatter().format(p.to_nominators),
+                );
+            }
+        }
+
+        Commands::ExposureHistory {
+            block,
+            eras,
+            concurrency,
+        } => {
+            let ah_rpc = Arc::new(connect_rpc(&ws, cli.http).await?);
+            let at: Hash = ah_rpc.get_block_hash(Some(block)).await?;
+            let ah_client = OnlineClient::<PolkadotConfig>::from_url(&ws).await?;
+
+            let by_era =
+                fetch_all_overviews_for_eras(&ah_client, ah_rpc, at, &eras, concurrency).await?;
+
+            // Regroup `era -> validator -> overview` as `validator -> era -> overview`.
+            let mut by_validator: BTreeMap<AccountId, BTreeMap<u32, &OnchainExposureOverview>> =
+                BTreeMap::new();
+            for (era, overviews) in &by_era {
+                println!("Era {}: {} validators exposed", era, overviews.len());
+                for (validator, overview) in overviews {
+                    by_validator
+                        .entry(*validator)
+                        .or_default()
+                        .insert(*era, overview);
+                }
+            }
+
+            println!(
+                "\nExposure history at AH block {} ({} validators):",
+                block,
+                by_validator.len()
+            );
+            for (validator, per_era) in &by_validator {
+                println!(
+                    "  {} exposed in {}/{} eras",
+                    account_formatter().fmt(validator),
+                    per_era.len(),
+                    by_era.len()
+                );
+                for era in by_era.keys() {
+                    match per_era.get(era) {
+                        Some(o) => println!(
+                            "    era {:>6} total={} own={} nominators={}",
+                            era, o.total, o.own, o.nominator_count
+                        ),
+                        None => println!("    era {:>6} -", era),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a `0x`-prefixed hex account id.
+fn parse_account(s: &str) -> Result<AccountId, String> {
+    crate::types::hex32::decode(s)
+}
+
+/// Read one hex account per line, skipping blank lines and `#` comments.
+fn read_account_list(path: &PathBuf) -> Result<BTreeSet<AccountId>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            parse_account(line).map_err(|e| anyhow::anyhow!("{}:{}: {}", path.display(), i + 1, e))
+        })
+        .collect()
+}
+
+/// Parse a `--sensitivity` percentage: finite and in `(0, 100]`.
+fn parse_sensitivity_pct(s: &str) -> Result<f64, String> {
+    let pct: f64 = s.parse().map_err(|e| format!("{s:?}: {e}"))?;
+    if pct.is_finite() && pct > 0.0 && pct <= 100.0 {
+        Ok(pct)
+    } else {
+        Err(format!("{s:?} is not a percentage in (0, 100]"))
+    }
+}
+
+/// Parse `minimal,sum,sum_sq` into an `ElectionScore`.
+fn parse_score(s: &str) -> Result<ElectionScore, String> {
+    let parts: Vec<u128> = s
+        .split(',')
+        .map(|p| p.trim().parse::<u128>().map_err(|e| format!("{p:?}: {e}")))
+        .collect::<Result<_, _>>()?;
+
+    match parts[..] {
+        [minimal_stake, sum_stake, sum_stake_squared] => Ok(ElectionScore {
+            minimal_stake,
+            sum_stake,
+            sum_stake_squared,
+        }),
+        _ => Err(format!(
+            "expected minimal,sum,sum_sq; got {} values",
+            parts.len()
+        )),
+    }
+}
+
+/// Fail unless `score` is component-wise at least as good as `floor`.
+/// Check `score` against the `--assert-min-support` and `--assert-score` floors.
+fn check_score_floors(
+    score: &ElectionScore,
+    min_support: Option<u128>,
+    floor: Option<&ElectionScore>,
+) -> Result<()> {
+    if let Some(min) = min_support
+        && score.minimal_stake < min
+    {
+        return Err(anyhow::anyhow!(
+            "score assertion failed: minimal support {} < required {}",
+            score.minimal_stake,
+            min
+        ));
     }
+    match floor {
+        Some(floor) => check_score_at_least(score, floor),
+        None => Ok(()),
+    }
+}
 
+fn check_score_at_least(score: &ElectionScore, floor: &ElectionScore) -> Result<()> {
+    let mut failures = Vec::new();
+
+    if score.minimal_stake < floor.minimal_stake {
+        failures.push(format!(
+            "minimal {} < {}",
+            score.minimal_stake, floor.minimal_stake
+        ));
+    }
+    if score.sum_stake < floor.sum_stake {
+        failures.push(format!("sum {} < {}", score.sum_stake, floor.sum_stake));
+    }
+    if score.sum_stake_squared > floor.sum_stake_squared {
+        failures.push(format!(
+            "sum_sq {} > {}",
+            score.sum_stake_squared, floor.sum_stake_squared
+        ));
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "score assertion failed: {}",
+            failures.join(", ")
+        ))
+    }
+}
+
+/// Print nonce and free/frozen balance of `who` at block `at`.
+///
+/// The frozen balance covers the staking lock, so it is a quick sanity check
+/// against the voter's snapshot weight.
+async fn print_account_info(rpc: &RpcClient, who: &AccountId, at: Hash) -> Result<()> {
+    let info = rpc.get_account_info(who, Some(at)).await?;
+    eprintln!(
+        "  ACCOUNT: nonce={} free={} frozen={}",
+        info.nonce, info.free, info.frozen
+    );
     Ok(())
 }
+
+/// Connect an `RpcClient` over HTTP if `http` is set, otherwise over WebSocket.
+async fn connect_rpc(uri: &str, http: bool) -> Result<RpcClient> {
+    if http {
+        RpcClient::connect_http(uri)
+    } else {
+        RpcClient::connect(uri).await
+    }
+}
+
+/// Progress bar over the `max_pages` target and `max_pages` voter page fetches.
+///
+/// Hidden when stdout is not a TTY, so piped or redirected runs stay quiet.
+fn snapshot_progress_bar(max_pages: u32) -> ProgressBar {
+    if !std::io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let progress = ProgressBar::new(u64::from(max_pages) * 2);
+    progress.set_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} pages {msg}")
+            .expect("valid progress template"),
+    );
+    progress
+}
+
+/// Warn when the offline winner count, `desired_targets` and the size of the on-chain
+/// set being compared against disagree, since the membership diff is then counted
+/// against different denominators (e.g. too few candidates to fill `desired_targets`).
+fn warn_winner_count_mismatch(source: &str, offline: usize, desired_targets: u32, onchain: usize) {
+    if offline != desired_targets as usize || onchain != desired_targets as usize {
+        eprintln!(
+            "[warn] winner counts diverge: offline={} desired_targets={} {}={}",
+            offline, desired_targets, source, onchain
+        );
+    }
+}
+
+/// Offline winner ranking extended `extra` seats past the snapshot's
+/// `desired_targets`, to place validators that just missed the cutoff.
+///
+/// Optional step of the relay comparison: it costs a second election run.
+/// Sequential phragmen elects the same prefix first, but supports are spread over
+/// more winners, so only the ranks beyond the cutoff should be taken from it.
+fn extended_offline_ranking(
+    snapshot: &ElectionSnapshot,
+    extra: usize,
+    reduce: bool,
+    pre_reduce: bool,
+    max_election_iterations: Option<usize>,
+    normalize_weights: bool,
+    max_targets_per_voter: Option<usize>,
+) -> Result<Vec<OfflineWinner>> {
+    let mut extended = snapshot.clone();
+    extended.desired_targets =
+        (snapshot.desired_targets as usize + extra).min(snapshot.all_targets.len()) as u32;
+    let outputs = run_offline_election_with_stake(
+        &extended,
+        reduce,
+        pre_reduce,
+        max_election_iterations.map(|n| n + extra),
+        normalize_weights,
+        max_targets_per_voter,
+    )?;
+    Ok(staked_assignments_to_offline_winners(
+        &outputs, false, false,
+    ))
+}
+
+/// Print a `BoundaryReport` as boundary debugging output.
+fn print_boundary_report(report: &BoundaryReport) {
+    eprintln!(
+        "BOUNDARY DEBUG: only_offline = {}, only_onchain = {}",
+        report.count(BoundarySide::OfflineOnly),
+        report.count(BoundarySide::OnchainOnly),
+    );
+
+    for entry in &report.entries {
+        match (entry.side, entry.rank, entry.support) {
+            (BoundarySide::OfflineOnly, Some(rank), Some(support)) => {
+                eprintln!(
+                    "  OFFLINE-ONLY {} at offline rank {} with support {}",
+                    account_formatter().fmt(&entry.validator),
+                    rank,
+                    support
+                );
+                eprintln!(
+                    "    Neighbours around that rank (±{}):",
+                    report.neighborhood
+                );
+                for n in &entry.neighbours {
+                    eprintln!(
+                        "      {} rank {:4} {} support={}",
+                        if n.rank == rank { ">>" } else { "  " },
+                        n.rank,
+                        account_formatter().fmt(&n.validator),
+                        n.support
+                    );
+                }
+            }
+            (BoundarySide::OfflineOnly, _, _) => {
+                eprintln!(
+                    "  OFFLINE-ONLY {} but not found in offline ranks (unexpected)",
+                    account_formatter().fmt(&entry.validator)
+                );
+            }
+            (BoundarySide::OnchainOnly, _, _) => {
+                eprintln!(
+                    "  ONCHAIN-ONLY {} did not appear in offline winners",
+                    account_formatter().fmt(&entry.validator)
+                );
+            }
+        }
+    }
+}
@@ -1,10 +1,16 @@
 // src/nominator_debug.rs
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
 
 use crate::offchain_exposures::RuntimeExposureMap;
 use crate::onchain_exposures::OnchainFlattenedExposures;
-use crate::types::{AccountId, Balance, balance_to_vote_weight};
+use crate::storage_keys::LedgerBalances;
+use crate::types::{
+    AccountId, Balance, ElectionSnapshot, VoteWeight, account_formatter, balance_formatter,
+    balance_to_vote_weight,
+};
 
 /// Nominator-centric view:
 /// nominator -> (validator -> stake).
@@ -47,12 +53,88 @@ pub fn build_onchain_nom_view(on: &OnchainFlattenedExposures) -> NomView {
     view
 }
 
+/// Validators `who` backs in `nom_view` with the stake assigned to each, largest first.
+pub fn find_validators_for_nominator(
+    who: &AccountId,
+    nom_view: &NomView,
+) -> Vec<(AccountId, Balance)> {
+    let mut found: Vec<(AccountId, Balance)> = nom_view
+        .get(who)
+        .map(|m| m.iter().map(|(v, s)| (*v, *s)).collect())
+        .unwrap_or_default();
+    found.sort_by_key(|(_, stake)| std::cmp::Reverse(*stake));
+    found
+}
+
 /// Print detailed per-nominator comparison between offline and on-chain exposures.
-pub fn debug_nominator(who: &AccountId, offline_nom_view: &NomView, onchain_nom_view: &NomView) {
+///
+/// With a `snapshot`, the nominator's snapshot entry is shown as well, which tells
+/// "voted but not assigned" apart from "not in the snapshot".
+///
+/// `ledger` is the result of reading the nominator's `Staking::Ledger` at the
+/// snapshot block (`None` if not bonded); its `active` stake is compared with the
+/// snapshot weight. A failed read is shown instead of ending the debug output.
+pub fn debug_nominator(
+    who: &AccountId,
+    offline_nom_view: &NomView,
+    onchain_nom_view: &NomView,
+    snapshot: Option<&ElectionSnapshot>,
+    ledger: &Result<Option<LedgerBalances>>,
+) {
     let off = offline_nom_view.get(who);
     let on = onchain_nom_view.get(who);
 
-    eprintln!("NOMINATOR 0x{}", hex::encode(who));
+    eprintln!("NOMINATOR {}", account_formatter().fmt(who));
+
+    let mut snapshot_weight: Option<VoteWeight> = None;
+    if let Some(snapshot) = snapshot {
+        match snapshot
+            .voter_pages
+            .iter()
+            .flatten()
+            .find(|v| v.who == *who)
+        {
+            Some(v) => {
+                let targets: Vec<String> = v
+                    .targets
+                    .iter()
+                    .map(|t| account_formatter().fmt(t))
+                    .collect();
+                eprintln!(
+                    "  SNAPSHOT: weight={}, targets=[{}]",
+                    v.weight,
+                    targets.join(", ")
+                );
+                snapshot_weight = Some(v.weight);
+            }
+            None => eprintln!("  SNAPSHOT: not found (not eligible to vote at this block)"),
+        }
+    }
+
+    match ledger {
+        Ok(Some(ledger)) => {
+            let active_vote = balance_to_vote_weight(ledger.active);
+            let verdict = match snapshot_weight {
+                Some(w) if w == active_vote => "matches snapshot weight".to_string(),
+                Some(w) => format!(
+                    "snapshot weight differs by {:+}",
+                    w as i128 - active_vote as i128
+                ),
+                None => "no snapshot weight".to_string(),
+            };
+            eprintln!(
+                "  LEDGER: controller={} total={} ({}) active={} ({}) -> {}",
+                account_formatter().fmt(&ledger.controller),
+                ledger.total,
+                balance_formatter().format(ledger.total),
+                ledger.active,
+                balance_formatter().format(ledger.active),
+                verdict,
+            );
+        }
+        Ok(None) => eprintln!("  LEDGER: not bonded"),
+        Err(e) => eprintln!("  LEDGER: unavailable ({e:#})"),
+    }
 
     let mut total_off: Balance = 0;
     let mut total_on: Balance = 0;
@@ -62,9 +144,10 @@ pub fn debug_nominator(who: &AccountId, offline_nom_view: &NomView, onchain_nom_
         for (val, stake) in map {
             total_off = total_off.saturating_add(*stake);
             eprintln!(
-                "    -> validator 0x{} stake={} vote={}",
-                hex::encode(val),
+                "    -> validator {} stake={} ({}) vote={}",
+                account_formatter().fmt(val),
                 stake,
+                balance_formatter().format(*stake),
                 balance_to_vote_weight(*stake),
             );
         }
@@ -77,9 +160,10 @@ pub fn debug_nominator(who: &AccountId, offline_nom_view: &NomView, onchain_nom_
         for (val, stake) in map {
             total_on = total_on.saturating_add(*stake);
             eprintln!(
-                "    -> validator 0x{} stake={} vote={}",
-                hex::encode(val),
+                "    -> validator {} stake={} ({}) vote={}",
+                account_formatter().fmt(val),
                 stake,
+                balance_formatter().format(*stake),
                 balance_to_vote_weight(*stake),
             );
         }
@@ -88,10 +172,72 @@ pub fn debug_nominator(who: &AccountId, offline_nom_view: &NomView, onchain_nom_
     }
 
     eprintln!(
-        "  TOTALS: off_total={} on_total={} off_vote={} on_vote={}",
+        "  TOTALS: off_total={} ({}) on_total={} ({}) off_vote={} on_vote={}",
         total_off,
+        balance_formatter().format(total_off),
         total_on,
+        balance_formatter().format(total_on),
         balance_to_vote_weight(total_off),
         balance_to_vote_weight(total_on),
     );
 }
+
+/// Backer churn and stake change for one validator between two exposure sets (A -> B).
+#[derive(Clone, Debug)]
+pub struct ValidatorChurn {
+    pub validator: AccountId,
+    /// Backers present in B but not in A.
+    pub joined: usize,
+    /// Backers present in A but not in B.
+    pub left: usize,
+    /// Backers present in both.
+    pub stayed: usize,
+    /// Total backing in A (0 if not exposed in A).
+    pub stake_a: Balance,
+    /// Total backing in B (0 if not exposed in B).
+    pub stake_b: Balance,
+}
+
+/// Invert a nominator-centric view into validator -> (nominator -> stake).
+fn by_validator(view: &NomView) -> BTreeMap<AccountId, BTreeMap<AccountId, Balance>> {
+    let mut out: BTreeMap<AccountId, BTreeMap<AccountId, Balance>> = BTreeMap::new();
+    for (nom, vals) in view {
+        for (val, stake) in vals {
+            out.entry(*val).or_default().insert(*nom, *stake);
+        }
+    }
+    out
+}
+
+/// Compare two nominator-centric views and report per-validator backer churn.
+///
+/// Covers every validator exposed in either view, ordered by `AccountId`.
+pub fn exposure_churn(a: &NomView, b: &NomView) -> Vec<ValidatorChurn> {
+    let a_by_val = by_validator(a);
+    let b_by_val = by_validator(b);
+    let empty = BTreeMap::new();
+
+    let validators: BTreeSet<AccountId> = a_by_val.keys().chain(b_by_val.keys()).copied().collect();
+
+    validators
+        .into_iter()
+        .map(|validator| {
+            let backers_a = a_by_val.get(&validator).unwrap_or(&empty);
+            let backers_b = b_by_val.get(&validator).unwrap_or(&empty);
+
+            let stayed = backers_a
+                .keys()
+                .filter(|n| backers_b.contains_key(*n))
+                .count();
+
+            ValidatorChurn {
+                validator,
+                joined: backers_b.len() - stayed,
+                left: backers_a.len() - stayed,
+                stayed,
+                stake_a: backers_a.values().fold(0, |acc, s| acc.saturating_add(*s)),
+                stake_b: backers_b.values().fold(0, |acc, s| acc.saturating_add(*s)),
+            }
+        })
+        .collect()
+}
@@ -3,13 +3,16 @@
 use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
+use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+use sp_arithmetic::{Perbill, Rounding};
 
 use crate::election::ElectionOutputs;
-use crate::types::{AccountId, Balance, ElectionSnapshot};
+use crate::types::{AccountId, Balance, hex32};
 
 /// Offline analogue of on-chain `IndividualExposure` in `Balance` units.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RuntimeBacker {
+    #[serde(with = "hex32")]
     pub who: AccountId,
     pub stake: Balance,
 }
@@ -20,6 +23,7 @@ pub struct RuntimeBacker {
 /// and `ErasStakersPaged` on AssetHub.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RuntimeExposure {
+    #[serde(with = "hex32")]
     pub validator: AccountId,
     pub total: Balance,
     pub own: Balance,
@@ -28,6 +32,43 @@ pub struct RuntimeExposure {
 
 pub type RuntimeExposureMap = BTreeMap<AccountId, RuntimeExposure>;
 
+/// Rank-ordered views and aggregates of a `RuntimeExposureMap`.
+pub trait RuntimeExposureMapExt {
+    /// Validators by `total` descending; equal totals keep `AccountId` order.
+    fn iter_sorted_by_support(&self) -> impl Iterator<Item = (&AccountId, &RuntimeExposure)>;
+
+    /// Sum of every validator's `total` (saturating).
+    fn total_stake(&self) -> Balance;
+
+    /// `sum(own) / sum(total)`: the share of the elected set's support that is
+    /// self-bonded rather than nominated; `0.0` for an empty map.
+    fn own_stake_fraction(&self) -> f64;
+}
+
+impl RuntimeExposureMapExt for RuntimeExposureMap {
+    fn iter_sorted_by_support(&self) -> impl Iterator<Item = (&AccountId, &RuntimeExposure)> {
+        let mut sorted: Vec<_> = self.iter().collect();
+        sorted.sort_by_key(|(_, e)| std::cmp::Reverse(e.total));
+        sorted.into_iter()
+    }
+
+    fn total_stake(&self) -> Balance {
+        self.values()
+            .fold(0, |acc: Balance, e| acc.saturating_add(e.total))
+    }
+
+    fn own_stake_fraction(&self) -> f64 {
+        let total = self.total_stake();
+        if total == 0 {
+            return 0.0;
+        }
+        let own = self
+            .values()
+            .fold(0, |acc: Balance, e| acc.saturating_add(e.own));
+        own as f64 / total as f64
+    }
+}
+
 /// Build runtime-like exposures from canonical `staked_assignments`.
 ///
 /// Mirrors on-chain behavior in:
@@ -36,27 +77,36 @@ pub type RuntimeExposureMap = BTreeMap<AccountId, RuntimeExposure>;
 ///
 /// Since `SaturatingCurrencyToVote::to_currency(value, _)` for `Balance = u128`
 /// amounts to a saturating conversion, each `share` (ExtendedBalance) is treated
-/// as a `Balance` with a saturating cast.
-pub fn build_runtime_exposures_from_staked(
-    _snapshot: &ElectionSnapshot,
-    outputs: &ElectionOutputs,
-) -> RuntimeExposureMap {
+/// as a `Balance` with a saturating cast. Should `ExtendedBalance` ever become
+/// wider than `Balance`, out-of-range shares saturate and are counted in a warning.
+///
+/// Zero shares are skipped, like on-chain; since `reduce` should never leave a
+/// zero-weight edge, their count is reported as well.
+pub fn build_runtime_exposures_from_staked(outputs: &ElectionOutputs) -> RuntimeExposureMap {
     let staked = outputs
         .staked_assignments
         .as_ref()
         .expect("build_runtime_exposures_from_staked called without staked_assignments");
 
     let mut map: RuntimeExposureMap = BTreeMap::new();
+    let mut truncated = 0usize;
+    let mut zero_shares = 0usize;
 
     for ass in staked {
         let nominator = ass.who;
 
         for (validator, share) in &ass.distribution {
             if *share == 0 {
+                zero_shares += 1;
                 continue;
             }
 
-            let stake_balance: Balance = *share as u128;
+            // `ExtendedBalance` is currently `u128`; keep the conversion fallible so a
+            // future widening saturates loudly instead of truncating silently.
+            let stake_balance: Balance = Balance::try_from(*share).unwrap_or_else(|_| {
+                truncated += 1;
+                Balance::MAX
+            });
 
             let entry = map.entry(*validator).or_insert(RuntimeExposure {
                 validator: *validator,
@@ -78,5 +128,76 @@ pub fn build_runtime_exposures_from_staked(
         }
     }
 
+    if truncated > 0 {
+        eprintln!(
+            "[warn] {} staked shares exceeded Balance::MAX and were saturated",
+            truncated
+        );
+    }
+
+    if zero_shares > 0 {
+        eprintln!(
+            "[warn] skipped {} zero-stake backers while building round {} exposures",
+            zero_shares, outputs.round
+        );
+    }
+
     map
 }
+
+/// Projected payout of one winner's slice of an era reward.
+#[derive(Clone, Debug)]
+pub struct RewardProjection {
+    pub validator: AccountId,
+    /// Total backing (`RuntimeExposure::total`).
+    pub support: Balance,
+    pub commission: Perbill,
+    /// The winner's slice of the era reward, proportional to `support`.
+    pub era_share: Balance,
+    /// Commission plus the self-stake's cut of what remains.
+    pub validator_take: Balance,
+    /// What remains for nominators, split among them by stake.
+    pub to_nominators: Balance,
+}
+
+/// Split `era_reward` across `exposures` proportionally to support, then each
+/// slice like `payout_stakers`: commission first, the rest by stake between the
+/// validator's own bond and its nominators.
+///
+/// On-chain the era reward follows era points rather than support, so this is an
+/// estimate for comparing winners, not a payout prediction. Validators missing
+/// from `commissions` are treated as 0% commission. Amounts round down, so the
+/// slices may sum to slightly less than `era_reward`. Ordered by support descending.
+pub fn project_rewards(
+    exposures: &RuntimeExposureMap,
+    commissions: &BTreeMap<AccountId, Perbill>,
+    era_reward: Balance,
+) -> Vec<RewardProjection> {
+    let total_support = exposures.total_stake();
+    let ratio = |amount: Balance, part: Balance, whole: Balance| -> Balance {
+        if whole == 0 {
+            return 0;
+        }
+        multiply_by_rational_with_rounding(amount, part, whole, Rounding::Down).unwrap_or(0)
+    };
+
+    exposures
+        .iter_sorted_by_support()
+        .map(|(validator, exp)| {
+            let commission = commissions.get(validator).copied().unwrap_or_default();
+            let era_share = ratio(era_reward, exp.total, total_support);
+            let commission_take = commission.mul_floor(era_share);
+            let remainder = era_share - commission_take;
+            let own_take = ratio(remainder, exp.own, exp.total);
+
+            RewardProjection {
+                validator: *validator,
+                support: exp.total,
+                commission,
+                era_share,
+                validator_take: commission_take + own_take,
+                to_nominators: remainder - own_take,
+            }
+        })
+        .collect()
+}
@@ -1,13 +1,19 @@
 // src/onchain_exposures.rs
 
 use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
+use sp_arithmetic::Perbill;
 use subxt::config::substrate::AccountId32;
 use subxt::utils::H256;
 use subxt::{OnlineClient, config::PolkadotConfig};
+use tokio::task::JoinSet;
 
 use crate::asset_hub;
+use crate::rpc::RpcClient;
+use crate::storage_keys::twox64_concat_key_hex;
 use crate::types::{AccountId, Balance, Hash};
 
 // Generated by subxt for pallet-staking-async:
@@ -101,6 +107,67 @@ pub async fn fetch_current_era_at(client: &OnlineClient<PolkadotConfig>, at: Has
     Ok(era)
 }
 
+/// Fetch the targets of `Staking::Nominators(who)` at a given block hash on AssetHub.
+///
+/// Returns `None` if `who` is not a nominator at that block.
+pub async fn fetch_nominations_for_account(
+    client: &OnlineClient<PolkadotConfig>,
+    at: Hash,
+    who: &AccountId,
+) -> Result<Option<Vec<AccountId>>> {
+    let storage = client.storage().at(H256::from(at));
+    let addr = asset_hub::api::storage()
+        .staking()
+        .nominators(local_to_account32(*who));
+
+    let nominations = storage.fetch(&addr).await?;
+    Ok(nominations.map(|n| n.targets.0.into_iter().map(account32_to_local).collect()))
+}
+
+/// Fetch `Staking::Validators` commission for each of `validators` at `at`.
+///
+/// Validators without registered prefs (chilled since the snapshot) are left out
+/// of the map. At most `max_concurrent` reads run at once, see `chunked_join_all`.
+pub async fn fetch_validator_commissions(
+    client: &OnlineClient<PolkadotConfig>,
+    at: Hash,
+    validators: &[AccountId],
+    max_concurrent: usize,
+) -> Result<BTreeMap<AccountId, Perbill>> {
+    let fetches: Vec<_> = validators
+        .iter()
+        .map(|&validator| {
+            let client = client.clone();
+            async move {
+                let addr = asset_hub::api::storage()
+                    .staking()
+                    .validators(local_to_account32(validator));
+                let prefs = client
+                    .storage()
+                    .at(H256::from(at))
+                    .fetch(&addr)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "fetching Staking::Validators for 0x{}",
+                            hex::encode(validator)
+                        )
+                    })?;
+                Ok::<_, anyhow::Error>((validator, prefs))
+            }
+        })
+        .collect();
+
+    let mut result = BTreeMap::new();
+    for fetched in chunked_join_all(fetches, max_concurrent).await {
+        let (validator, prefs) = fetched?;
+        if let Some(prefs) = prefs {
+            result.insert(validator, Perbill::from_parts(prefs.commission.0));
+        }
+    }
+    Ok(result)
+}
+
 /// Fetch on-chain paged exposures from `pallet-staking-async` (`Staking`)
 /// for a given era and set of validators at a specific block.
 ///
@@ -108,9 +175,19 @@ pub async fn fetch_current_era_at(client: &OnlineClient<PolkadotConfig>, at: Has
 /// - `at`: `[u8; 32]` block hash.
 /// - `era`: era index to inspect.
 /// - `validators`: winners / candidates to fetch exposures for.
+/// - `overviews`: `ErasStakersOverview` for the same era, used to bound the page scan.
 /// - `max_pages_per_validator`: safety bound (for example: 16, 32 or a runtime upper bound).
+/// - `max_concurrent`: validators fetched concurrently, see `chunked_join_all`.
 ///
-/// Iteration stops for a validator at the first `None` page.
+/// Exposure pages are packed contiguously from page 0, so for each validator only
+/// `0..min(page_count, max_pages_per_validator)` is probed and iteration stops at
+/// the first `None` page. Validators without an overview have no exposure and are
+/// skipped without any page reads.
+///
+/// After collecting a validator's pages, each page's backers are checked against
+/// its `page_total` and a disagreement returns an error. The nominator count is
+/// not checked here: callers compare it to the overview's `nominator_count`
+/// themselves and decide whether a short fetch is fatal (`--strict-exposures`).
 ///
 /// Matches `ErasStakersPaged(era, stash, page)` where:
 ///   `ErasStakersPaged = BoundedExposurePage`
@@ -120,58 +197,146 @@ pub async fn fetch_onchain_exposures_for_era(
     at: Hash,
     era: u32,
     validators: &[AccountId],
+    overviews: &OnchainExposureOverviewMap,
     max_pages_per_validator: u32,
+    max_concurrent: usize,
 ) -> Result<OnchainExposurePagesMap> {
-    let at_hash = H256::from(at);
-
-    let storage = client.storage().at(at_hash);
-    let root_storage = asset_hub::api::storage();
-    let staking = root_storage.staking();
+    let fetches: Vec<_> = validators
+        .iter()
+        .filter_map(|validator| {
+            let overview = overviews.get(validator)?.clone();
+            let client = client.clone();
+            let validator = *validator;
+            Some(async move {
+                let pages = fetch_exposure_pages(
+                    &client,
+                    at,
+                    era,
+                    validator,
+                    &overview,
+                    max_pages_per_validator,
+                )
+                .await?;
+                Ok::<_, anyhow::Error>((validator, pages))
+            })
+        })
+        .collect();
 
     let mut result: OnchainExposurePagesMap = BTreeMap::new();
+    for fetched in chunked_join_all(fetches, max_concurrent).await {
+        let (validator, pages) = fetched?;
+        if !pages.is_empty() {
+            result.insert(validator, pages);
+        }
+    }
 
-    for validator in validators {
-        let stash_runtime = local_to_account32(*validator);
-        let mut pages: Vec<OnchainExposurePage> = Vec::new();
-
-        // Iterate pages 0..max_pages_per_validator and stop at first `None`.
-        for page_index in 0..max_pages_per_validator {
-            let addr = staking.eras_stakers_paged(era, stash_runtime.clone(), page_index);
-
-            let page_opt = storage.fetch(&addr).await?;
-
-            let Some(bounded_page) = page_opt else {
-                break;
-            };
+    Ok(result)
+}
 
-            // BoundedExposurePage(pub ExposurePage<AccountId32, u128>).
-            let inner = bounded_page.0;
+/// Fetch and check the exposure pages of one validator, see
+/// `fetch_onchain_exposures_for_era`.
+async fn fetch_exposure_pages(
+    client: &OnlineClient<PolkadotConfig>,
+    at: Hash,
+    era: u32,
+    validator: AccountId,
+    overview: &OnchainExposureOverview,
+    max_pages_per_validator: u32,
+) -> Result<Vec<OnchainExposurePage>> {
+    let storage = client.storage().at(H256::from(at));
+    let staking = asset_hub::api::storage().staking();
+
+    let stash_runtime = local_to_account32(validator);
+    let mut pages: Vec<OnchainExposurePage> = Vec::new();
+
+    // Iterate the pages announced by the overview and stop at first `None`.
+    let page_bound = overview.page_count.min(max_pages_per_validator);
+    for page_index in 0..page_bound {
+        let addr = staking.eras_stakers_paged(era, stash_runtime.clone(), page_index);
+
+        let page_opt = storage.fetch(&addr).await?;
+
+        let Some(bounded_page) = page_opt else {
+            break;
+        };
+
+        // BoundedExposurePage(pub ExposurePage<AccountId32, u128>).
+        let inner = bounded_page.0;
+
+        let page_total: Balance = inner.page_total;
+
+        let others: Vec<OnchainBacker> = inner
+            .others
+            .into_iter()
+            .map(|ind| OnchainBacker {
+                who: account32_to_local(ind.who),
+                stake: ind.value,
+            })
+            .collect();
+
+        pages.push(OnchainExposurePage {
+            validator,
+            page_index,
+            page_total,
+            others,
+        });
+    }
 
-            let page_total: Balance = inner.page_total;
+    check_page_totals(&pages)?;
 
-            let others: Vec<OnchainBacker> = inner
-                .others
-                .into_iter()
-                .map(|ind| OnchainBacker {
-                    who: account32_to_local(ind.who),
-                    stake: ind.value,
-                })
-                .collect();
+    Ok(pages)
+}
 
-            pages.push(OnchainExposurePage {
-                validator: *validator,
-                page_index,
-                page_total,
-                others,
-            });
+/// Await `futures` in batches of `chunk_size`: each batch runs concurrently on a
+/// `JoinSet` and completes before the next batch starts, so at most `chunk_size`
+/// requests hit the node at once. Outputs keep the order of `futures`.
+///
+/// A panicking future re-raises its panic here.
+pub async fn chunked_join_all<F, T>(futures: Vec<F>, chunk_size: usize) -> Vec<T>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let mut outputs: Vec<Option<T>> = Vec::with_capacity(futures.len());
+    outputs.resize_with(futures.len(), || None);
+
+    let mut futures = futures.into_iter().enumerate().peekable();
+    while futures.peek().is_some() {
+        let mut batch = JoinSet::new();
+        for (i, fut) in futures.by_ref().take(chunk_size.max(1)) {
+            batch.spawn(async move { (i, fut.await) });
         }
+        while let Some(joined) = batch.join_next().await {
+            let (i, out) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            outputs[i] = Some(out);
+        }
+    }
 
-        if !pages.is_empty() {
-            result.insert(*validator, pages);
+    outputs
+        .into_iter()
+        .map(|out| out.expect("every batch is awaited to completion"))
+        .collect()
+}
+
+/// Confirm that each fetched exposure page's backers sum to its `page_total`.
+fn check_page_totals(pages: &[OnchainExposurePage]) -> Result<()> {
+    for page in pages {
+        let sum = page
+            .others
+            .iter()
+            .fold(0u128, |acc, b| acc.saturating_add(b.stake));
+        if sum != page.page_total {
+            return Err(anyhow!(
+                "Validator 0x{} page {}: backers sum to {} but page_total is {}",
+                hex::encode(page.validator),
+                page.page_index,
+                sum,
+                page.page_total,
+            ));
         }
     }
 
-    Ok(result)
+    Ok(())
 }
 
 /// Fetch `ErasStakersOverview[era][validator]` on AssetHub.
@@ -230,6 +395,96 @@ pub async fn fetch_overviews_for_validators(
     Ok(map)
 }
 
+/// Number of keys requested per `state_getKeysPaged` call.
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+/// Fetch `ErasStakersOverview(era, *)` for every validator exposed in `era`.
+///
+/// Enumerates the double-map prefix for `era` via `state_getKeysPaged` and decodes
+/// the validator from the key suffix, so the validator set need not be known upfront.
+///
+/// Key layout (both hashers are `Twox64Concat`):
+///   `prefix(32) ++ twox64(era)(8) ++ era(4) ++ twox64(stash)(8) ++ stash(32)`.
+pub async fn fetch_all_overviews(
+    client: &OnlineClient<PolkadotConfig>,
+    rpc: &RpcClient,
+    at: Hash,
+    era: u32,
+) -> Result<OnchainExposureOverviewMap> {
+    const KEY_LEN: usize = 32 + 8 + 4 + 8 + 32;
+
+    let prefix = twox64_concat_key_hex("Staking", "ErasStakersOverview", &era.to_le_bytes());
+
+    let mut validators: Vec<AccountId> = Vec::new();
+    let mut start_key: Option<String> = None;
+
+    loop {
+        let keys = rpc
+            .get_keys_paged_bytes(&prefix, KEYS_PAGE_SIZE, start_key.as_deref(), Some(at))
+            .await?;
+
+        for bytes in &keys {
+            if bytes.len() != KEY_LEN {
+                return Err(anyhow!(
+                    "unexpected ErasStakersOverview key length {}, expected {}",
+                    bytes.len(),
+                    KEY_LEN
+                ));
+            }
+            let mut stash = [0u8; 32];
+            stash.copy_from_slice(&bytes[KEY_LEN - 32..]);
+            validators.push(stash);
+        }
+
+        if keys.len() < KEYS_PAGE_SIZE as usize {
+            break;
+        }
+        start_key = keys.last().map(|k| format!("0x{}", hex::encode(k)));
+    }
+
+    fetch_overviews_for_validators(client, at, era, &validators).await
+}
+
+/// Fetch `ErasStakersOverview` for several eras concurrently via `fetch_all_overviews`.
+///
+/// A single `client` and `rpc` connection are shared by all eras; at most
+/// `concurrency` eras are in flight at once, since each era's reads are independent.
+pub async fn fetch_all_overviews_for_eras(
+    client: &OnlineClient<PolkadotConfig>,
+    rpc: Arc<RpcClient>,
+    at: Hash,
+    eras: &[u32],
+    concurrency: usize,
+) -> Result<BTreeMap<u32, OnchainExposureOverviewMap>> {
+    let mut result = BTreeMap::new();
+    let mut in_flight = JoinSet::new();
+
+    for &era in eras {
+        if in_flight.len() >= concurrency.max(1)
+            && let Some(joined) = in_flight.join_next().await
+        {
+            let (era, overviews) = joined??;
+            result.insert(era, overviews);
+        }
+
+        let client = client.clone();
+        let rpc = Arc::clone(&rpc);
+        in_flight.spawn(async move {
+            let overviews = fetch_all_overviews(&client, &rpc, at, era)
+                .await
+                .with_context(|| format!("fetching ErasStakersOverview for era {}", era))?;
+            Ok::<_, anyhow::Error>((era, overviews))
+        });
+    }
+
+    while let Some(joined) = in_flight.join_next().await {
+        let (era, overviews) = joined??;
+        result.insert(era, overviews);
+    }
+
+    Ok(result)
+}
+
 /// Flatten paged exposures into a per-validator, per-nominator stake map.
 ///
 /// Produces:
@@ -238,19 +493,162 @@ pub async fn fetch_overviews_for_validators(
 pub type OnchainBackerMap = BTreeMap<AccountId, Balance>;
 pub type OnchainFlattenedExposures = BTreeMap<AccountId, OnchainBackerMap>;
 
-pub fn flatten_onchain_backers(pages: &OnchainExposurePagesMap) -> OnchainFlattenedExposures {
+/// Nominator listed more than once in a validator's exposure pages.
+///
+/// Should not happen on a healthy chain; it hints at corrupted storage.
+#[derive(Clone, Debug)]
+pub struct DuplicateOnchainNominator {
+    pub validator: AccountId,
+    pub nominator: AccountId,
+    /// Page of the first occurrence.
+    pub page_a: u32,
+    /// Page of the repeated occurrence.
+    pub page_b: u32,
+}
+
+/// Flatten `pages`; a nominator appearing more than once for the same validator has
+/// its stakes summed (saturating) and is reported as a `DuplicateOnchainNominator`.
+pub fn flatten_onchain_backers(
+    pages: &OnchainExposurePagesMap,
+) -> (OnchainFlattenedExposures, Vec<DuplicateOnchainNominator>) {
     let mut result: OnchainFlattenedExposures = BTreeMap::new();
+    let mut duplicates = Vec::new();
 
     for (validator, v_pages) in pages {
-        let entry = result.entry(*validator).or_insert_with(BTreeMap::new);
+        let entry = result.entry(*validator).or_default();
+        let mut first_page: BTreeMap<AccountId, u32> = BTreeMap::new();
 
         for page in v_pages {
             for backer in &page.others {
+                match first_page.entry(backer.who) {
+                    Entry::Vacant(v) => {
+                        v.insert(page.page_index);
+                    }
+                    Entry::Occupied(o) => duplicates.push(DuplicateOnchainNominator {
+                        validator: *validator,
+                        nominator: backer.who,
+                        page_a: *o.get(),
+                        page_b: page.page_index,
+                    }),
+                }
                 let stake_entry = entry.entry(backer.who).or_insert(0u128);
                 *stake_entry = stake_entry.saturating_add(backer.stake);
             }
         }
     }
 
-    result
+    (result, duplicates)
+}
+
+/// Validator whose flattened on-chain exposure does not add up to its overview total.
+#[derive(Clone, Debug)]
+pub struct ExposureTotalError {
+    pub validator: AccountId,
+    /// Sum of the flattened nominator stakes across all fetched pages.
+    pub flat_sum: Balance,
+    /// `ErasStakersOverview.total` (own + nominators).
+    pub overview_total: Balance,
+    /// `flat_sum + own - overview_total`.
+    pub delta: i128,
+}
+
+/// Check that, for every flattened validator, `own` plus the nominator stakes equals
+/// the overview `total`. Returns one entry per validator with a non-zero delta.
+///
+/// A non-zero delta means either an incomplete page fetch or inconsistent node storage.
+pub fn verify_onchain_exposure_totals(
+    flat: &OnchainFlattenedExposures,
+    overviews: &OnchainExposureOverviewMap,
+) -> Vec<ExposureTotalError> {
+    let mut errors = Vec::new();
+
+    for (validator, backers) in flat {
+        let Some(overview) = overviews.get(validator) else {
+            continue;
+        };
+
+        let flat_sum = backers
+            .values()
+            .fold(0u128, |acc, stake| acc.saturating_add(*stake));
+        let delta = (flat_sum as i128)
+            .saturating_add(overview.own as i128)
+            .saturating_sub(overview.total as i128);
+
+        if delta != 0 {
+            errors.push(ExposureTotalError {
+                validator: *validator,
+                flat_sum,
+                overview_total: overview.total,
+                delta,
+            });
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nominator_debug::build_onchain_nom_view;
+
+    fn page(
+        validator: AccountId,
+        page_index: u32,
+        others: &[(AccountId, Balance)],
+    ) -> OnchainExposurePage {
+        OnchainExposurePage {
+            validator,
+            page_index,
+            page_total: others
+                .iter()
+                .fold(0, |acc: Balance, (_, s)| acc.saturating_add(*s)),
+            others: others
+                .iter()
+                .map(|&(who, stake)| OnchainBacker { who, stake })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn duplicate_nominator_across_pages_is_summed_and_reported() {
+        let validator = [1u8; 32];
+        let dup = [2u8; 32];
+        let other = [3u8; 32];
+
+        let mut pages: OnchainExposurePagesMap = BTreeMap::new();
+        pages.insert(
+            validator,
+            vec![
+                page(validator, 0, &[(dup, Balance::MAX - 5), (other, 7)]),
+                page(validator, 1, &[(dup, 10)]),
+            ],
+        );
+
+        let (flat, duplicates) = flatten_onchain_backers(&pages);
+
+        let view = build_onchain_nom_view(&flat);
+        assert_eq!(view[&dup][&validator], Balance::MAX);
+        assert_eq!(view[&other][&validator], 7);
+
+        assert_eq!(duplicates.len(), 1);
+        let d = &duplicates[0];
+        assert_eq!(d.validator, validator);
+        assert_eq!(d.nominator, dup);
+        assert_eq!((d.page_a, d.page_b), (0, 1));
+    }
+
+    #[test]
+    fn page_total_mismatch_is_an_error() {
+        let validator = [1u8; 32];
+        let mut pages = vec![
+            page(validator, 0, &[([2u8; 32], 5)]),
+            page(validator, 1, &[([3u8; 32], 7)]),
+        ];
+        assert!(check_page_totals(&pages).is_ok());
+
+        pages[1].page_total = 8;
+        let err = check_page_totals(&pages).unwrap_err().to_string();
+        assert!(err.contains("page 1"), "{err}");
+    }
 }
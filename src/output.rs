@@ -0,0 +1,380 @@
+// src/output.rs
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use serde::Serialize;
+use sp_npos_elections::ElectionScore;
+
+use crate::types::{
+    AccountFormatter, ElectionSnapshot, Hash, OfflineWinner, account_formatter, balance_formatter,
+    hex32, snapshot_from_json, snapshot_to_json,
+};
+
+static REPORT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Send `report!` output to stderr instead of stdout, so stdout carries only
+/// machine-readable output (`--format jsonl`).
+pub fn set_report_to_stderr(enabled: bool) {
+    REPORT_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `report!` currently writes to stderr.
+pub fn report_to_stderr() -> bool {
+    REPORT_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// `println!` for human-readable reports; goes to stderr after
+/// `set_report_to_stderr(true)`.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        if $crate::output::report_to_stderr() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use report;
+
+/// Write `snapshot` as JSON to `out`: stdout for `-`, `<snapshot_id>.json` inside `out`
+/// if it is a directory, otherwise the file `out` (atomically).
+///
+/// The JSON is read back first and nothing is written unless it round-trips.
+pub fn snapshot_output(snapshot: &ElectionSnapshot, out: &Path) -> Result<()> {
+    let json = snapshot_to_json(snapshot)?;
+    if snapshot_from_json(&json)? != snapshot.with_checksum() {
+        return Err(anyhow::anyhow!(
+            "snapshot {} does not round-trip through JSON; not writing it",
+            snapshot.snapshot_id()
+        ));
+    }
+
+    if out.as_os_str() == "-" {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(json.as_bytes())?;
+        stdout.write_all(b"\n")?;
+        return Ok(());
+    }
+
+    let path = if out.is_dir() {
+        out.join(format!("{}.json", snapshot.snapshot_id()))
+    } else {
+        out.to_path_buf()
+    };
+    write_atomic(&path, json.as_bytes())?;
+    eprintln!("Snapshot written to {}", path.display());
+    Ok(())
+}
+
+/// Write `data` to `{path}.tmp` and rename it over `path`, so an interrupted
+/// write never leaves a truncated file behind (the rename is atomic on POSIX).
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, data)?;
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(anyhow::anyhow!(
+            "failed to move {} to {}: {}",
+            tmp.display(),
+            path.display(),
+            e
+        ));
+    }
+    Ok(())
+}
+
+/// `winners` as bare addresses, one per line in the given order (`--winners-list`).
+pub fn winners_address_list(winners: &[OfflineWinner], formatter: &AccountFormatter) -> String {
+    winners
+        .iter()
+        .map(|w| format!("{}\n", formatter.fmt(&w.validator)))
+        .collect()
+}
+
+/// Stream `winners` to `out` as NDJSON, one compact JSON object per line
+/// (`--format jsonl`). Each line has the shape of a `--winners-out` entry.
+pub fn write_winners_jsonl(winners: &[OfflineWinner], mut out: impl Write) -> Result<()> {
+    for w in winners {
+        serde_json::to_writer(&mut out, w)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Everything `run-offline` computed, as one JSON document (`--summary-out`).
+///
+/// Two runs over the same snapshot with the same flags produce identical summaries;
+/// `timings_ms` is only included with `--timing`.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    pub snapshot: SnapshotSummary,
+    pub algorithm: String,
+    pub reduce: bool,
+    pub pre_reduce: bool,
+    /// `--normalize-weights`: supports and score are in normalized units, not plancks.
+    pub normalize_weights: bool,
+    pub desired_targets: u32,
+    pub winner_count: usize,
+    pub assigned_fraction: f64,
+    pub score: ScoreSummary,
+    /// `--assert-min-support` and `--assert-score` floors, if given.
+    pub min_support_floor: Option<u128>,
+    pub score_floor: Option<ScoreSummary>,
+    /// Whether the score met all given floors; `None` without floors. The run still
+    /// fails after writing the summary when this is `false`.
+    pub floors_met: Option<bool>,
+    pub relay: Option<RelaySummary>,
+    pub exposures: Option<ExposureSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings_ms: Option<TimingsSummary>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SnapshotSummary {
+    pub id: String,
+    #[serde(with = "hex32")]
+    pub at: Hash,
+    pub round: u32,
+    pub total_issuance: u128,
+    pub voter_pages: usize,
+    pub voters: usize,
+    pub targets: usize,
+}
+
+impl SnapshotSummary {
+    pub fn from_snapshot(snapshot: &ElectionSnapshot) -> Self {
+        Self {
+            id: snapshot.snapshot_id(),
+            at: snapshot.at,
+            round: snapshot.round,
+            total_issuance: snapshot.total_issuance,
+            voter_pages: snapshot.voter_pages.len(),
+            voters: snapshot.voter_pages.iter().map(Vec::len).sum(),
+            targets: snapshot.all_targets.len(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScoreSummary {
+    pub minimal: u128,
+    pub sum: u128,
+    pub sum_sq: u128,
+}
+
+impl From<&ElectionScore> for ScoreSummary {
+    fn from(score: &ElectionScore) -> Self {
+        Self {
+            minimal: score.minimal_stake,
+            sum: score.sum_stake,
+            sum_sq: score.sum_stake_squared,
+        }
+    }
+}
+
+/// Offline winners vs a relay validator set (`--compare-block`).
+#[derive(Clone, Debug, Serialize)]
+pub struct RelaySummary {
+    pub block: u32,
+    /// Relay storage item the set was read from.
+    pub source: String,
+    pub matched: usize,
+    pub only_offline: usize,
+    pub only_onchain: usize,
+}
+
+/// Offline vs on-chain exposures (`--debug-exposures`).
+#[derive(Clone, Debug, Serialize)]
+pub struct ExposureSummary {
+    pub era: u32,
+    pub matched_nominator_sets: usize,
+    pub mismatched_nominator_sets: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TimingsSummary {
+    /// Election algorithm and `reduce` only (`ElectionOutputs::computation_time_ms`).
+    pub computation: u64,
+    pub election: u128,
+    pub total: u128,
+}
+
+/// Wall time of each `run-offline` step (`--timing`). Steps that did not run, e.g.
+/// the exposure fetch without `--debug-exposures`, stay `0`.
+#[derive(Clone, Debug, Default)]
+pub struct RunTimings {
+    pub snapshot_load_ms: u128,
+    pub flatten_voters_ms: u128,
+    pub seq_phragmen_ms: u128,
+    pub ratio_to_staked_ms: u128,
+    pub reduce_ms: u128,
+    pub staked_to_ratio_ms: u128,
+    pub verification_ms: u128,
+    pub exposure_build_ms: u128,
+    pub exposure_fetch_ms: u128,
+    pub relay_compare_ms: u128,
+    pub total_ms: u128,
+}
+
+impl RunTimings {
+    /// One line per step with its milliseconds and share of `total_ms`.
+    pub fn render_table(&self) -> String {
+        use std::fmt::Write;
+
+        let rows = [
+            ("snapshot_load_ms", self.snapshot_load_ms),
+            ("flatten_voters_ms", self.flatten_voters_ms),
+            ("seq_phragmen_ms", self.seq_phragmen_ms),
+            ("ratio_to_staked_ms", self.ratio_to_staked_ms),
+            ("reduce_ms", self.reduce_ms),
+            ("staked_to_ratio_ms", self.staked_to_ratio_ms),
+            ("verification_ms", self.verification_ms),
+            ("exposure_build_ms", self.exposure_build_ms),
+            ("exposure_fetch_ms", self.exposure_fetch_ms),
+            ("relay_compare_ms", self.relay_compare_ms),
+            ("total_ms", self.total_ms),
+        ];
+
+        let mut table = String::new();
+        let _ = writeln!(table, "{:<20} {:>10} {:>7}", "step", "ms", "share");
+        for (step, ms) in rows {
+            let share = ms as f64 * 100.0 / self.total_ms.max(1) as f64;
+            let _ = writeln!(table, "{:<20} {:>10} {:>6.1}%", step, ms, share);
+        }
+        table
+    }
+}
+
+/// Shareable Markdown version of a run (`--report-md`).
+pub struct MarkdownReport<'a> {
+    pub summary: &'a RunSummary,
+    /// Winners in election rank order.
+    pub winners: &'a [OfflineWinner],
+}
+
+impl MarkdownReport<'_> {
+    pub fn render(&self) -> String {
+        use std::fmt::Write;
+
+        let s = self.summary;
+        let mut md = String::new();
+        // Writing into a `String` cannot fail.
+        let _ = writeln!(md, "# Offline election report: {}\n", s.snapshot.id);
+
+        let _ = writeln!(md, "## Overview\n");
+        let _ = writeln!(md, "| | |\n|---|---|");
+        let _ = writeln!(
+            md,
+            "| Snapshot block | `0x{}` |",
+            hex::encode(s.snapshot.at)
+        );
+        let _ = writeln!(md, "| Round | {} |", s.snapshot.round);
+        let _ = writeln!(
+            md,
+            "| Total issuance | {} |",
+            balance_formatter().format(s.snapshot.total_issuance)
+        );
+        let _ = writeln!(
+            md,
+            "| Voters / targets | {} in {} pages / {} |",
+            s.snapshot.voters, s.snapshot.voter_pages, s.snapshot.targets
+        );
+        let _ = writeln!(md, "| Algorithm | {} |", s.algorithm);
+        let _ = writeln!(
+            md,
+            "| Reduce / pre-reduce | {} / {} |",
+            s.reduce, s.pre_reduce
+        );
+        let _ = writeln!(md, "| Normalized weights | {} |", s.normalize_weights);
+        let _ = writeln!(
+            md,
+            "| Winners / desired | {} / {} |",
+            s.winner_count, s.desired_targets
+        );
+        let _ = writeln!(
+            md,
+            "| Assigned voter weight | {:.2}% |\n",
+            s.assigned_fraction * 100.0
+        );
+
+        let _ = writeln!(md, "## Winners\n");
+        let _ = writeln!(
+            md,
+            "| Rank | Validator | Support | Backers |\n|---:|---|---:|---:|"
+        );
+        for (rank, w) in self.winners.iter().enumerate() {
+            let _ = writeln!(
+                md,
+                "| {} | `{}` | {} | {} |",
+                rank + 1,
+                account_formatter().fmt(&w.validator),
+                if s.normalize_weights {
+                    w.support.to_string()
+                } else {
+                    balance_formatter().format(w.support as u128)
+                },
+                w.backers.len()
+            );
+        }
+
+        let _ = writeln!(md, "\n## Score Analysis\n");
+        let _ = writeln!(md, "| | Score | Floor |\n|---|---:|---:|");
+        let floor = s.score_floor.as_ref();
+        let _ = writeln!(
+            md,
+            "| Minimal stake | {} | {} |",
+            s.score.minimal,
+            floor
+                .map(|f| f.minimal)
+                .or(s.min_support_floor)
+                .map_or("-".to_string(), |v| v.to_string())
+        );
+        let _ = writeln!(
+            md,
+            "| Sum stake | {} | {} |",
+            s.score.sum,
+            floor.map_or("-".to_string(), |f| f.sum.to_string())
+        );
+        let _ = writeln!(
+            md,
+            "| Sum stake squared | {} | {} |",
+            s.score.sum_sq,
+            floor.map_or("-".to_string(), |f| f.sum_sq.to_string())
+        );
+        if let Some(met) = s.floors_met {
+            let _ = writeln!(
+                md,
+                "\nScore floors: {}",
+                if met { "met" } else { "**NOT met**" }
+            );
+        }
+
+        if let Some(e) = &s.exposures {
+            let _ = writeln!(md, "\n## Exposure Comparison Summary\n");
+            let _ = writeln!(
+                md,
+                "Era {}: nominator sets matched for {} validators, mismatched for {}.",
+                e.era, e.matched_nominator_sets, e.mismatched_nominator_sets
+            );
+        }
+
+        if let Some(r) = &s.relay {
+            let _ = writeln!(md, "\n## Relay Comparison\n");
+            let _ = writeln!(
+                md,
+                "Against `{}` at relay block {}: {} matched, {} only offline, {} only on-chain.",
+                r.source, r.block, r.matched, r.only_offline, r.only_onchain
+            );
+        }
+
+        md
+    }
+}
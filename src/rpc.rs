@@ -1,21 +1,162 @@
 // src/rpc.rs
 use anyhow::{Result, anyhow};
-use jsonrpsee::core::client::ClientT;
+use futures::{Stream, StreamExt};
+use jsonrpsee::core::client::{ClientT, SubscriptionClientT};
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
 use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
 use parity_scale_codec::Decode;
+use serde::de::DeserializeOwned;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use subxt::config::substrate::AccountId32;
+
+use crate::storage_keys::system_account_key;
+use crate::types::{AccountId, Balance};
 
 pub type Hash = [u8; 32];
 
-/// Thin wrapper around a JSON-RPC WS client.
+/// Subset of `frame_system::AccountInfo` relevant for submitting transactions.
+#[derive(Clone, Debug)]
+pub struct AccountInfo {
+    pub nonce: u32,
+    pub free: Balance,
+    pub frozen: Balance,
+}
+
+/// SCALE layout of `AccountInfo<u32, AccountData<u128>>` as stored in `System::Account`.
+#[derive(Decode, Default)]
+struct RawAccountInfo {
+    nonce: u32,
+    _consumers: u32,
+    _providers: u32,
+    _sufficients: u32,
+    free: u128,
+    _reserved: u128,
+    frozen: u128,
+    _flags: u128,
+}
+
+/// A block given on the command line: a number, or `finalized`.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockSpec {
+    Number(u32),
+    Finalized,
+}
+
+impl std::str::FromStr for BlockSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "finalized" => Ok(BlockSpec::Finalized),
+            n => n
+                .parse()
+                .map(BlockSpec::Number)
+                .map_err(|e| format!("expected a block number or `finalized`: {e}")),
+        }
+    }
+}
+
+impl std::fmt::Display for BlockSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockSpec::Number(n) => write!(f, "{n}"),
+            BlockSpec::Finalized => f.write_str("finalized"),
+        }
+    }
+}
+
+/// A block announced by `RpcClient::subscribe_finalized_heads`.
+#[derive(Clone, Copy, Debug)]
+pub struct FinalizedHead {
+    pub number: u32,
+    pub hash: Hash,
+}
+
+/// The only header field the finalized-heads subscription needs.
+#[derive(serde::Deserialize)]
+struct RawHeader {
+    /// Hex-encoded (`0x...`) block number.
+    number: String,
+}
+
+/// Requests sent by all `RpcClient`s of this process.
+static RPC_CALLS: AtomicU64 = AtomicU64::new(0);
+/// Storage value bytes returned by `get_storage` across all `RpcClient`s.
+static RPC_BYTES: AtomicU64 = AtomicU64::new(0);
+/// Request timeout of `RpcClient`s connected from now on, see `RpcClient::set_request_timeout`.
+static RPC_REQUEST_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Process-wide `RpcClient` counters, see `RpcClient::stats`.
+#[derive(Clone, Copy, Debug)]
+pub struct RpcStats {
+    pub calls: u64,
+    pub bytes: u64,
+}
+
+/// Underlying JSON-RPC transport of an `RpcClient`.
+pub(crate) enum RpcClientInner {
+    Ws(WsClient),
+    /// Request/response only; subscriptions are not supported over HTTP.
+    Http(Box<HttpClient>),
+}
+
+/// Thin wrapper around a JSON-RPC WS or HTTP client.
 pub struct RpcClient {
-    pub(crate) inner: WsClient,
+    pub(crate) inner: RpcClientInner,
 }
 
 impl RpcClient {
     /// Connect to a node via WebSocket.
     pub async fn connect(uri: &str) -> Result<Self> {
-        let inner = WsClientBuilder::default().build(uri).await?;
-        Ok(Self { inner })
+        let mut builder = WsClientBuilder::default();
+        if let Some(timeout) = RPC_REQUEST_TIMEOUT.get() {
+            builder = builder.request_timeout(*timeout);
+        }
+        let inner = builder.build(uri).await?;
+        Ok(Self {
+            inner: RpcClientInner::Ws(inner),
+        })
+    }
+
+    /// Connect to a node via HTTP, for nodes that expose no WS endpoint.
+    pub fn connect_http(uri: &str) -> Result<Self> {
+        let mut builder = HttpClientBuilder::default();
+        if let Some(timeout) = RPC_REQUEST_TIMEOUT.get() {
+            builder = builder.request_timeout(*timeout);
+        }
+        let inner = builder.build(uri)?;
+        Ok(Self {
+            inner: RpcClientInner::Http(Box::new(inner)),
+        })
+    }
+
+    /// Use `timeout` instead of the jsonrpsee default (60s) for requests of every client
+    /// connected afterwards. Only the first call has an effect.
+    pub fn set_request_timeout(timeout: Duration) {
+        let _ = RPC_REQUEST_TIMEOUT.set(timeout);
+    }
+
+    /// Requests made and storage bytes fetched by every `RpcClient` so far.
+    ///
+    /// Reads that go through subxt are not counted.
+    pub fn stats() -> RpcStats {
+        RpcStats {
+            calls: RPC_CALLS.load(Ordering::Relaxed),
+            bytes: RPC_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Send a request over whichever transport this client uses.
+    async fn request<R: DeserializeOwned>(&self, method: &str, params: ArrayParams) -> Result<R> {
+        RPC_CALLS.fetch_add(1, Ordering::Relaxed);
+        let res = match &self.inner {
+            RpcClientInner::Ws(client) => client.request(method, params).await?,
+            RpcClientInner::Http(client) => client.request(method, params).await?,
+        };
+        Ok(res)
     }
 
     /// `state_getStorage` wrapper.
@@ -29,29 +170,50 @@ impl RpcClient {
             jsonrpsee::rpc_params![key]
         };
 
-        let res: Option<String> = self.inner.request("state_getStorage", params).await?;
+        let res: Option<String> = self.request("state_getStorage", params).await?;
 
         let decoded = res.map(|hex_str| {
             let s = hex_str.trim_start_matches("0x");
             hex::decode(s).expect("RPC returned invalid hex")
         });
+        if let Some(bytes) = &decoded {
+            RPC_BYTES.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
 
         Ok(decoded)
     }
 
     /// Decode storage at a key into a type `T: Decode`.
+    ///
+    /// With `RUST_LOG=debug`, the raw value is logged before decoding, so a failed or
+    /// surprising decode can be checked against what the node actually returned.
     pub async fn get_storage_decoded<T: Decode>(
         &self,
         key_hex: &str,
         at: Option<Hash>,
     ) -> Result<Option<T>> {
-        if let Some(bytes) = self.get_storage(key_hex, at).await? {
-            let mut slice = &bytes[..];
-            let value = T::decode(&mut slice).map_err(|e| anyhow!("decode error: {:?}", e))?;
-            Ok(Some(value))
-        } else {
-            Ok(None)
-        }
+        let at_label = at.map_or_else(|| "best".to_string(), |h| format!("0x{}", hex::encode(h)));
+        self.get_storage(key_hex, at)
+            .await?
+            .map(|bytes| decode_storage_value(key_hex, &at_label, &bytes))
+            .transpose()
+    }
+
+    /// `get_storage` at the current finalized head.
+    pub async fn get_storage_at_finalized(&self, key_hex: &str) -> Result<Option<Vec<u8>>> {
+        let at = self.get_finalized_head().await?;
+        self.get_storage(key_hex, Some(at)).await
+    }
+
+    /// `get_storage_decoded` at the current finalized head.
+    pub async fn get_storage_decoded_at_finalized<T: Decode>(
+        &self,
+        key_hex: &str,
+    ) -> Result<Option<T>> {
+        self.get_storage_at_finalized(key_hex)
+            .await?
+            .map(|bytes| decode_storage_value(key_hex, "finalized", &bytes))
+            .transpose()
     }
 
     /// `state_getKeysPaged` wrapper that is block-aware.
@@ -66,39 +228,105 @@ impl RpcClient {
 
         let keys: Vec<String> = match (start_key, at) {
             (None, None) => {
-                self.inner
-                    .request("state_getKeysPaged", rpc_params![prefix_hex, count])
+                self.request("state_getKeysPaged", rpc_params![prefix_hex, count])
                     .await?
             }
             (Some(start), None) => {
-                self.inner
-                    .request("state_getKeysPaged", rpc_params![prefix_hex, count, start])
+                self.request("state_getKeysPaged", rpc_params![prefix_hex, count, start])
                     .await?
             }
             (None, Some(hash)) => {
                 let hash_hex = format!("0x{}", hex::encode(hash));
                 let start: Option<String> = None;
-                self.inner
-                    .request(
-                        "state_getKeysPaged",
-                        rpc_params![prefix_hex, count, start, hash_hex],
-                    )
-                    .await?
+                self.request(
+                    "state_getKeysPaged",
+                    rpc_params![prefix_hex, count, start, hash_hex],
+                )
+                .await?
             }
             (Some(start), Some(hash)) => {
                 let hash_hex = format!("0x{}", hex::encode(hash));
-                self.inner
-                    .request(
-                        "state_getKeysPaged",
-                        rpc_params![prefix_hex, count, start, hash_hex],
-                    )
-                    .await?
+                self.request(
+                    "state_getKeysPaged",
+                    rpc_params![prefix_hex, count, start, hash_hex],
+                )
+                .await?
             }
         };
 
         Ok(keys)
     }
 
+    /// `get_keys_paged` with the returned keys decoded to raw bytes, for callers that
+    /// slice SCALE-encoded suffixes out of the keys.
+    pub async fn get_keys_paged_bytes(
+        &self,
+        prefix_hex: &str,
+        count: u32,
+        start_key: Option<&str>,
+        at: Option<Hash>,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.get_keys_paged(prefix_hex, count, start_key, at)
+            .await?
+            .iter()
+            .map(|key| {
+                hex::decode(key.trim_start_matches("0x"))
+                    .map_err(|e| anyhow!("RPC returned invalid key hex {}: {}", key, e))
+            })
+            .collect()
+    }
+
+    /// Next transaction index (nonce) of `account`.
+    ///
+    /// - `at = None`    -> `system_accountNextIndex`, which includes pending pool transactions.
+    /// - `at = Some(h)` -> nonce stored in `System::Account` at block `h`.
+    pub async fn get_nonce(&self, account: &AccountId, at: Option<Hash>) -> Result<u32> {
+        if at.is_some() {
+            return Ok(self.get_raw_account_info(account, at).await?.nonce);
+        }
+
+        let who = AccountId32::from(*account);
+        let nonce: u32 = self
+            .request("system_accountNextIndex", jsonrpsee::rpc_params![who])
+            .await?;
+        Ok(nonce)
+    }
+
+    /// Nonce and free/frozen balance of `account`.
+    ///
+    /// The nonce is resolved via `get_nonce`, the balances from `System::Account`.
+    pub async fn get_account_info(
+        &self,
+        account: &AccountId,
+        at: Option<Hash>,
+    ) -> Result<AccountInfo> {
+        let raw = self.get_raw_account_info(account, at).await?;
+        let nonce = match at {
+            Some(_) => raw.nonce,
+            None => self.get_nonce(account, None).await?,
+        };
+
+        Ok(AccountInfo {
+            nonce,
+            free: raw.free,
+            frozen: raw.frozen,
+        })
+    }
+
+    /// Read `System::Account(account)`; missing entries decode as the all-zero default.
+    async fn get_raw_account_info(
+        &self,
+        account: &AccountId,
+        at: Option<Hash>,
+    ) -> Result<RawAccountInfo> {
+        let key = system_account_key(account);
+        let raw = self
+            .get_storage_decoded::<RawAccountInfo>(&key, at)
+            .await?
+            .unwrap_or_default();
+        Ok(raw)
+    }
+
     /// `chain_getBlockHash` wrapper.
     ///
     /// - `number = Some(n)` -> block hash at height `n`.
@@ -110,19 +338,79 @@ impl RpcClient {
             jsonrpsee::rpc_params![]
         };
 
-        let res: Option<String> = self.inner.request("chain_getBlockHash", params).await?;
+        let res: Option<String> = self.request("chain_getBlockHash", params).await?;
         let hex = res.ok_or_else(|| anyhow!("chain_getBlockHash returned null"))?;
+        parse_hash(&hex)
+    }
+
+    /// `chain_getFinalizedHead` wrapper.
+    pub async fn get_finalized_head(&self) -> Result<Hash> {
+        let hex: String = self
+            .request("chain_getFinalizedHead", jsonrpsee::rpc_params![])
+            .await?;
+        parse_hash(&hex)
+    }
 
-        let bytes = hex::decode(hex.trim_start_matches("0x"))?;
-        if bytes.len() != 32 {
-            return Err(anyhow!(
-                "unexpected hash length {}, expected 32",
-                bytes.len()
-            ));
+    /// `chain_subscribeFinalizedHeads` as a stream of `(number, hash)`.
+    ///
+    /// Headers do not carry their own hash, so each one is resolved with
+    /// `chain_getBlockHash`. Requires a WS connection.
+    pub async fn subscribe_finalized_heads(
+        &self,
+    ) -> Result<impl Stream<Item = Result<FinalizedHead>> + '_> {
+        let RpcClientInner::Ws(client) = &self.inner else {
+            return Err(anyhow!("subscriptions not supported over HTTP"));
+        };
+        let sub = client
+            .subscribe::<RawHeader, _>(
+                "chain_subscribeFinalizedHeads",
+                jsonrpsee::rpc_params![],
+                "chain_unsubscribeFinalizedHeads",
+            )
+            .await?;
+
+        Ok(sub.then(move |header| async move {
+            let number = u32::from_str_radix(header?.number.trim_start_matches("0x"), 16)?;
+            let hash = self.get_block_hash(Some(number)).await?;
+            Ok(FinalizedHead { number, hash })
+        }))
+    }
+
+    /// Resolve a `BlockSpec` to a block hash; `None` is the best block.
+    pub async fn resolve_block(&self, block: Option<BlockSpec>) -> Result<Hash> {
+        match block {
+            Some(BlockSpec::Number(n)) => self.get_block_hash(Some(n)).await,
+            Some(BlockSpec::Finalized) => self.get_finalized_head().await,
+            None => self.get_block_hash(None).await,
         }
+    }
+}
 
-        let mut h = [0u8; 32];
-        h.copy_from_slice(&bytes);
-        Ok(h)
+/// Decode a raw storage value, logging it first at debug level.
+fn decode_storage_value<T: Decode>(key_hex: &str, at: &str, bytes: &[u8]) -> Result<T> {
+    tracing::debug!(
+        key = key_hex,
+        at,
+        len = bytes.len(),
+        head = %hex::encode(&bytes[..bytes.len().min(16)]),
+        "decoding storage value as {}",
+        std::any::type_name::<T>(),
+    );
+    let mut slice = bytes;
+    T::decode(&mut slice).map_err(|e| anyhow!("decode error: {:?}", e))
+}
+
+/// Parse a `0x`-prefixed 32-byte block hash returned by the node.
+fn parse_hash(hex: &str) -> Result<Hash> {
+    let bytes = hex::decode(hex.trim_start_matches("0x"))?;
+    if bytes.len() != 32 {
+        return Err(anyhow!(
+            "unexpected hash length {}, expected 32",
+            bytes.len()
+        ));
     }
+
+    let mut h = [0u8; 32];
+    h.copy_from_slice(&bytes);
+    Ok(h)
 }
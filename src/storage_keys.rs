@@ -1,8 +1,13 @@
 // src/storage_keys.rs
-use crate::rpc::RpcClient;
-use crate::types::Hash;
+use std::sync::Arc;
+
+use crate::onchain_exposures::chunked_join_all;
+use crate::rpc::{BlockSpec, RpcClient};
+use crate::types::AccountId;
+use crate::types::{Balance, Hash};
 use anyhow::{Result, anyhow};
-use sp_core::hashing::twox_128;
+use parity_scale_codec::Decode;
+use sp_core::hashing::{blake2_128, twox_64, twox_128};
 
 /// 32-byte prefix = `Twox128("Module") ++ Twox128("StorageItem")`.
 pub fn plain_prefix(module: &str, storage: &str) -> [u8; 32] {
@@ -17,15 +22,326 @@ pub fn plain_key_hex(module: &str, storage: &str) -> String {
     format!("0x{}", hex::encode(prefix))
 }
 
-/// Read `CurrentEra` at the given AssetHub block.
-pub async fn planning_era_at_ah_block(ah_rpc: &RpcClient, ah_block: u32) -> Result<u32> {
-    let ah_hash: Hash = ah_rpc.get_block_hash(Some(ah_block)).await?;
+/// Key of a `Blake2_128Concat`-hashed map entry:
+/// `Twox128("Module") ++ Twox128("StorageItem") ++ Blake2_128(key) ++ key`.
+pub fn blake2_128_concat_key_hex(module: &str, storage: &str, key: &[u8]) -> String {
+    let mut out = plain_prefix(module, storage).to_vec();
+    out.extend_from_slice(&blake2_128(key));
+    out.extend_from_slice(key);
+    format!("0x{}", hex::encode(out))
+}
+
+/// Prefix of a `Twox64Concat`-hashed (double) map, fixing the first key:
+/// `Twox128("Module") ++ Twox128("StorageItem") ++ Twox64(key) ++ key`.
+pub fn twox64_concat_key_hex(module: &str, storage: &str, key: &[u8]) -> String {
+    let mut out = plain_prefix(module, storage).to_vec();
+    out.extend_from_slice(&twox_64(key));
+    out.extend_from_slice(key);
+    format!("0x{}", hex::encode(out))
+}
+
+/// `System::Account(who)`.
+pub fn system_account_key(who: &AccountId) -> String {
+    blake2_128_concat_key_hex("System", "Account", who)
+}
+
+/// `Session::Validators`
+/// (`0xcec5070d609dd3497f72bde07fc96ba088dcde934c658227ee1dfafcd6e16903`).
+pub fn session_validators_key() -> String {
+    plain_key_hex("Session", "Validators")
+}
 
-    let key = plain_key_hex("Staking", "CurrentEra");
+/// `Session::QueuedKeys`
+/// (`0xcec5070d609dd3497f72bde07fc96ba0e0cdd062e6eaf24295ad4ccfc41d4609`).
+pub fn session_queued_keys_key() -> String {
+    plain_key_hex("Session", "QueuedKeys")
+}
 
-    let val: Option<u32> = ah_rpc
-        .get_storage_decoded::<u32>(&key, Some(ah_hash))
-        .await?;
+/// `Staking::CurrentEra`
+/// (`0x5f3e4907f716ac89b6347d15ececedca0b6a45321efae92aea15e0740ec7afe7`).
+pub fn staking_current_era_key() -> String {
+    plain_key_hex("Staking", "CurrentEra")
+}
+
+/// `Staking::ActiveEra`
+/// (`0x5f3e4907f716ac89b6347d15ececedca487df464e44a534ba6b0cbb32407b587`).
+pub fn staking_active_era_key() -> String {
+    plain_key_hex("Staking", "ActiveEra")
+}
+
+/// `Staking::ValidatorCount`
+/// (`0x5f3e4907f716ac89b6347d15ececedca138e71612491192d68deab7e6f563fe1`).
+pub fn staking_validator_count_key() -> String {
+    plain_key_hex("Staking", "ValidatorCount")
+}
+
+/// `Balances::TotalIssuance`
+/// (`0xc2261276cc9d1f8598ea4b6a74b15c2f57c875e4cff74148e4628f264b974c80`).
+pub fn balances_total_issuance_key() -> String {
+    plain_key_hex("Balances", "TotalIssuance")
+}
+
+/// `Session::QueuedChanged` (relay chain).
+pub fn session_queued_changed_key() -> String {
+    plain_key_hex("Session", "QueuedChanged")
+}
+
+/// `Staking::Validators(who)` (`ValidatorPrefs` of a registered validator).
+pub fn staking_validators_key(who: &AccountId) -> String {
+    twox64_concat_key_hex("Staking", "Validators", who)
+}
+
+/// `Staking::Bonded(stash)` (the stash's controller account).
+pub fn staking_bonded_key(stash: &AccountId) -> String {
+    twox64_concat_key_hex("Staking", "Bonded", stash)
+}
+
+/// `Staking::Ledger(controller)`.
+pub fn staking_ledger_key(controller: &AccountId) -> String {
+    blake2_128_concat_key_hex("Staking", "Ledger", controller)
+}
+
+/// `read_u32_at` for a `BlockSpec`; `finalized` reads at the finalized head.
+async fn read_u32_at_ah_block(
+    ah_rpc: &RpcClient,
+    key: &str,
+    ah_block: BlockSpec,
+) -> Result<Option<u32>> {
+    match ah_block {
+        BlockSpec::Number(n) => {
+            let ah_hash: Hash = ah_rpc.get_block_hash(Some(n)).await?;
+            read_u32_at(ah_rpc, key, ah_hash).await
+        }
+        BlockSpec::Finalized => ah_rpc.get_storage_decoded_at_finalized::<u32>(key).await,
+    }
+}
+
+/// Read `CurrentEra` at the given AssetHub block.
+pub async fn planning_era_at_ah_block(ah_rpc: &RpcClient, ah_block: BlockSpec) -> Result<u32> {
+    let val = read_u32_at_ah_block(ah_rpc, &staking_current_era_key(), ah_block).await?;
 
     val.ok_or_else(|| anyhow!("CurrentEra not found at AH block {}", ah_block))
 }
+
+/// Read the `ActiveEra` index at the given AssetHub block.
+///
+/// `ActiveEraInfo { index, start }` is SCALE-encoded with `index` first, so
+/// decoding a leading `u32` yields the index.
+pub async fn active_era_at_ah_block(ah_rpc: &RpcClient, ah_block: BlockSpec) -> Result<u32> {
+    let val = read_u32_at_ah_block(ah_rpc, &staking_active_era_key(), ah_block).await?;
+
+    val.ok_or_else(|| anyhow!("ActiveEra not found at AH block {}", ah_block))
+}
+
+/// Read a `u32` storage value at `key` and the given block hash.
+async fn read_u32_at(rpc: &RpcClient, key: &str, at: Hash) -> Result<Option<u32>> {
+    rpc.get_storage_decoded::<u32>(key, Some(at)).await
+}
+
+/// Read a `u128` storage value at `key` and the given block hash.
+async fn read_u128_at(rpc: &RpcClient, key: &str, at: Hash) -> Result<Option<u128>> {
+    rpc.get_storage_decoded::<u128>(key, Some(at)).await
+}
+
+/// Read a `bool` storage value at `key` and the given block hash.
+async fn read_bool_at(rpc: &RpcClient, key: &str, at: Hash) -> Result<Option<bool>> {
+    rpc.get_storage_decoded::<bool>(key, Some(at)).await
+}
+
+/// Read `Balances::TotalIssuance` at the given block hash.
+pub async fn total_issuance_at(rpc: &RpcClient, at: Hash) -> Result<Option<u128>> {
+    read_u128_at(rpc, &balances_total_issuance_key(), at).await
+}
+
+/// Read `Staking::ValidatorCount` at the given block hash.
+pub async fn validator_count_at(rpc: &RpcClient, at: Hash) -> Result<Option<u32>> {
+    read_u32_at(rpc, &staking_validator_count_key(), at).await
+}
+
+/// Read relay `Session::QueuedChanged` at the given block hash: whether the queued
+/// keys differ from the current session's.
+pub async fn session_queued_changed_at(rpc: &RpcClient, at: Hash) -> Result<Option<bool>> {
+    read_bool_at(rpc, &session_queued_changed_key(), at).await
+}
+
+/// Whether `who` has a `Staking::Validators` (`ValidatorPrefs`) entry at the given block hash.
+pub async fn is_registered_validator_at(
+    rpc: &RpcClient,
+    at: Hash,
+    who: &AccountId,
+) -> Result<bool> {
+    Ok(rpc
+        .get_storage(&staking_validators_key(who), Some(at))
+        .await?
+        .is_some())
+}
+
+/// Bonded amounts of a stash's `Staking::Ledger` entry.
+#[derive(Clone, Debug)]
+pub struct LedgerBalances {
+    pub controller: AccountId,
+    /// Bonded, including chunks still unlocking.
+    pub total: Balance,
+    /// Bonded and not unlocking; what the snapshot weight is derived from.
+    pub active: Balance,
+}
+
+/// Leading fields of `StakingLedger { stash, total, active, unlocking, .. }`;
+/// the rest of the entry is not decoded.
+#[derive(Decode)]
+struct RawLedgerHead {
+    _stash: AccountId,
+    #[codec(compact)]
+    total: Balance,
+    #[codec(compact)]
+    active: Balance,
+}
+
+/// Read `stash`'s ledger at the given block hash: `Staking::Bonded(stash)` gives the
+/// controller, `Staking::Ledger(controller)` the bonded amounts. `None` if not bonded.
+pub async fn staking_ledger_at(
+    rpc: &RpcClient,
+    at: Hash,
+    stash: &AccountId,
+) -> Result<Option<LedgerBalances>> {
+    let Some(controller) = rpc
+        .get_storage_decoded::<AccountId>(&staking_bonded_key(stash), Some(at))
+        .await?
+    else {
+        return Ok(None);
+    };
+    let ledger = rpc
+        .get_storage_decoded::<RawLedgerHead>(&staking_ledger_key(&controller), Some(at))
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "Staking::Bonded points 0x{} at controller 0x{} without a Staking::Ledger entry",
+                hex::encode(stash),
+                hex::encode(controller)
+            )
+        })?;
+
+    Ok(Some(LedgerBalances {
+        controller,
+        total: ledger.total,
+        active: ledger.active,
+    }))
+}
+
+/// Which of `targets` have no `Staking::Validators` entry at the given block hash,
+/// in input order. At most `max_concurrent` reads run at once, see `chunked_join_all`.
+pub async fn unregistered_validators_at(
+    rpc: Arc<RpcClient>,
+    at: Hash,
+    targets: &[AccountId],
+    max_concurrent: usize,
+) -> Result<Vec<AccountId>> {
+    let reads: Vec<_> = targets
+        .iter()
+        .map(|&target| {
+            let rpc = rpc.clone();
+            async move {
+                let registered = is_registered_validator_at(&rpc, at, &target).await?;
+                Ok::<_, anyhow::Error>((target, registered))
+            }
+        })
+        .collect();
+
+    let mut unregistered = Vec::new();
+    for read in chunked_join_all(reads, max_concurrent).await {
+        let (target, registered) = read?;
+        if !registered {
+            unregistered.push(target);
+        }
+    }
+    Ok(unregistered)
+}
+
+/// Newest storage version of each AssetHub pallet whose keys this module builds by
+/// hand. Newer on-chain versions may have moved or re-encoded those items.
+pub const SUPPORTED_PALLET_VERSIONS: &[(&str, u16)] = &[("Staking", 16), ("Balances", 1)];
+
+/// `Twox128("Pallet") ++ Twox128(":__STORAGE_VERSION__:")`, the `StorageVersion` key.
+pub fn pallet_version_key(pallet: &str) -> String {
+    plain_key_hex(pallet, ":__STORAGE_VERSION__:")
+}
+
+/// Read a pallet's on-chain `StorageVersion`; `None` if the pallet never set one.
+pub async fn fetch_pallet_version(rpc: &RpcClient, pallet: &str, at: Hash) -> Result<Option<u16>> {
+    rpc.get_storage_decoded::<u16>(&pallet_version_key(pallet), Some(at))
+        .await
+}
+
+/// Pallets from `SUPPORTED_PALLET_VERSIONS` whose on-chain version is newer than
+/// supported, as `(pallet, on_chain, supported)`.
+pub async fn outdated_pallet_versions(
+    rpc: &RpcClient,
+    at: Hash,
+) -> Result<Vec<(&'static str, u16, u16)>> {
+    let mut outdated = Vec::new();
+    for &(pallet, supported) in SUPPORTED_PALLET_VERSIONS {
+        if let Some(on_chain) = fetch_pallet_version(rpc, pallet, at).await?
+            && on_chain > supported
+        {
+            outdated.push((pallet, on_chain, supported));
+        }
+    }
+    Ok(outdated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_keys_match_known_hex() {
+        assert_eq!(
+            session_validators_key(),
+            "0xcec5070d609dd3497f72bde07fc96ba088dcde934c658227ee1dfafcd6e16903"
+        );
+        assert_eq!(
+            session_queued_keys_key(),
+            "0xcec5070d609dd3497f72bde07fc96ba0e0cdd062e6eaf24295ad4ccfc41d4609"
+        );
+    }
+
+    #[test]
+    fn staking_era_keys_match_known_hex() {
+        assert_eq!(
+            staking_current_era_key(),
+            "0x5f3e4907f716ac89b6347d15ececedca0b6a45321efae92aea15e0740ec7afe7"
+        );
+        assert_eq!(
+            staking_active_era_key(),
+            "0x5f3e4907f716ac89b6347d15ececedca487df464e44a534ba6b0cbb32407b587"
+        );
+    }
+
+    #[test]
+    fn plain_value_keys_match_known_hex() {
+        assert_eq!(
+            balances_total_issuance_key(),
+            "0xc2261276cc9d1f8598ea4b6a74b15c2f57c875e4cff74148e4628f264b974c80"
+        );
+        assert_eq!(
+            staking_validator_count_key(),
+            "0x5f3e4907f716ac89b6347d15ececedca138e71612491192d68deab7e6f563fe1"
+        );
+    }
+
+    #[test]
+    fn map_keys_append_hashed_key() {
+        let who: AccountId = [7u8; 32];
+        let prefix = plain_key_hex("Staking", "Validators");
+        let key = staking_validators_key(&who);
+        assert!(key.starts_with(&prefix));
+        // prefix ++ twox64(who) (8 bytes) ++ who (32 bytes)
+        assert_eq!(key.len(), prefix.len() + 2 * (8 + 32));
+        assert!(key.ends_with(&hex::encode(who)));
+
+        let key = system_account_key(&who);
+        // prefix ++ blake2_128(who) (16 bytes) ++ who (32 bytes)
+        assert_eq!(key.len(), prefix.len() + 2 * (16 + 32));
+        assert!(key.ends_with(&hex::encode(who)));
+    }
+}
@@ -1,6 +1,9 @@
 // src/types.rs
+use parity_scale_codec::Encode;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use sp_core::hashing::blake2_256;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
 
 /// 32-byte block hash.
 pub type Hash = [u8; 32];
@@ -14,19 +17,81 @@ pub type Balance = u128;
 /// Vote weight type used by EPMB snapshots (`u64`).
 pub type VoteWeight = u64;
 
+/// Serde adapter rendering a 32-byte `Hash` / `AccountId` as a `"0x..."` hex string.
+///
+/// Use with `#[serde(with = "hex32")]`.
+pub mod hex32 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 32], D::Error> {
+        let s = String::deserialize(d)?;
+        decode(&s).map_err(D::Error::custom)
+    }
+
+    /// Parse a (optionally `0x`-prefixed) 64-char hex string into 32 bytes.
+    pub fn decode(s: &str) -> Result<[u8; 32], String> {
+        let bytes = hex::decode(s.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+        bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| format!("expected 32 bytes, got {}", b.len()))
+    }
+}
+
+/// Serde adapter for `Vec<AccountId>` rendered as a list of hex strings.
+///
+/// Use with `#[serde(with = "hex32_vec")]`.
+pub mod hex32_vec {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error, ser::SerializeSeq};
+
+    pub fn serialize<S: Serializer>(ids: &[[u8; 32]], s: S) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(ids.len()))?;
+        for id in ids {
+            seq.serialize_element(&format!("0x{}", hex::encode(id)))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        let strings = Vec::<String>::deserialize(d)?;
+        strings
+            .iter()
+            .map(|s| super::hex32::decode(s).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
 /// Single voter entry as exposed in the multi-block election snapshot:
 ///
 /// `(who, weight, targets)`
 ///
 /// Mirrors the type:
 ///   `(AccountId, VoteWeight, BoundedVec<AccountId, MaxVotesPerVoter>)`.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VoterSnapshot {
+    #[serde(with = "hex32")]
     pub who: AccountId,
     pub weight: VoteWeight,
+    #[serde(with = "hex32_vec")]
     pub targets: Vec<AccountId>,
 }
 
+impl VoterSnapshot {
+    /// Return a copy of this voter with `targets` sorted by `AccountId`.
+    pub fn with_sorted_targets(&self) -> VoterSnapshot {
+        let mut targets = self.targets.clone();
+        targets.sort();
+        VoterSnapshot {
+            who: self.who,
+            weight: self.weight,
+            targets,
+        }
+    }
+}
+
 /// High-level mirror of what the miner sees via `Snapshot::<T>`:
 ///
 /// - `all_targets`  ≈ `Snapshot::<T>::targets()`
@@ -34,9 +99,14 @@ pub struct VoterSnapshot {
 /// - `desired_targets` ≈ `Snapshot::<T>::desired_targets()`
 ///
 /// This is the structure consumed by the offline election.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ElectionSnapshot {
+    /// JSON layout version; always `SNAPSHOT_SCHEMA_VERSION` once loaded.
+    /// Absent in v2 files written before the field existed.
+    #[serde(default)]
+    pub schema_version: u32,
     /// Block hash at which the snapshot was read.
+    #[serde(with = "hex32")]
     pub at: Hash,
     /// Multi-block election round index.
     pub round: u32,
@@ -45,34 +115,621 @@ pub struct ElectionSnapshot {
     /// Desired number of winners for this round.
     pub desired_targets: u32,
     /// All candidate targets considered by the election.
+    #[serde(with = "hex32_vec")]
     pub all_targets: Vec<AccountId>,
     /// Paged voters, exactly as exposed by the EPMB snapshot (per-page).
     pub voter_pages: Vec<Vec<VoterSnapshot>>,
+    /// `0x`-prefixed BLAKE2b-256 of the SCALE-encoded other fields, as written by
+    /// `snapshot_to_json`. `None` for snapshots built or derived in memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl ElectionSnapshot {
+    /// Short human-friendly identifier `r{round}-{first 8 hex chars of at}`,
+    /// e.g. `r5-a3f1b2c4`.
+    pub fn snapshot_id(&self) -> String {
+        format!("r{}-{}", self.round, hex::encode(&self.at[..4]))
+    }
+
+    /// `0x`-prefixed BLAKE2b-256 of the SCALE encoding of every field except
+    /// `checksum`, in declaration order.
+    pub fn compute_checksum(&self) -> String {
+        let voter_pages: Vec<Vec<(AccountId, VoteWeight, &Vec<AccountId>)>> = self
+            .voter_pages
+            .iter()
+            .map(|page| page.iter().map(|v| (v.who, v.weight, &v.targets)).collect())
+            .collect();
+        let encoded = (
+            self.schema_version,
+            self.at,
+            self.round,
+            self.total_issuance,
+            self.desired_targets,
+            &self.all_targets,
+            voter_pages,
+        )
+            .encode();
+        format!("0x{}", hex::encode(blake2_256(&encoded)))
+    }
+
+    /// Return a copy of this snapshot with `checksum` set to `compute_checksum()`.
+    pub fn with_checksum(&self) -> ElectionSnapshot {
+        ElectionSnapshot {
+            checksum: Some(self.compute_checksum()),
+            ..self.clone()
+        }
+    }
+
+    /// Return the canonical form of this snapshot: `all_targets` sorted, every page
+    /// sorted by `who`, and every voter's `targets` sorted.
+    ///
+    /// Two snapshots that differ only in ordering have the same canonical form. Page
+    /// boundaries are kept, since they are part of the election input.
+    pub fn canonicalize(&self) -> ElectionSnapshot {
+        let mut all_targets = self.all_targets.clone();
+        all_targets.sort();
+
+        let voter_pages = self
+            .voter_pages
+            .iter()
+            .map(|page| {
+                let mut page: Vec<VoterSnapshot> = page
+                    .iter()
+                    .map(VoterSnapshot::with_sorted_targets)
+                    .collect();
+                page.sort_by_key(|v| v.who);
+                page
+            })
+            .collect();
+
+        ElectionSnapshot {
+            schema_version: self.schema_version,
+            at: self.at,
+            round: self.round,
+            total_issuance: self.total_issuance,
+            desired_targets: self.desired_targets,
+            all_targets,
+            voter_pages,
+            checksum: None,
+        }
+    }
+
+    /// Whether `who` is in `all_targets`.
+    pub fn is_target(&self, who: &AccountId) -> bool {
+        self.all_targets.contains(who)
+    }
+
+    /// Whether `who` is a voter on any page.
+    pub fn is_voter(&self, who: &AccountId) -> bool {
+        self.voter_pages.iter().flatten().any(|v| v.who == *who)
+    }
+
+    /// Set of all voter accounts across every page.
+    ///
+    /// Recomputed on each call; callers needing it repeatedly should keep the result.
+    pub fn voter_set(&self) -> BTreeSet<AccountId> {
+        self.voter_pages.iter().flatten().map(|v| v.who).collect()
+    }
+
+    /// Role of `who` in this snapshot.
+    pub fn role(&self, who: &AccountId) -> AccountRole {
+        match (self.is_target(who), self.is_voter(who)) {
+            (true, true) => AccountRole::Both,
+            (true, false) => AccountRole::TargetOnly,
+            (false, true) => AccountRole::VoterOnly,
+            (false, false) => AccountRole::None,
+        }
+    }
+
+    /// Whether any voter page is empty.
+    pub fn has_empty_pages(&self) -> bool {
+        self.voter_pages.iter().any(Vec::is_empty)
+    }
+
+    /// Return a copy of this snapshot without empty voter pages.
+    ///
+    /// Empty pages show up when the page scan runs past the pages the chain actually
+    /// holds; they add no voters, only size and a misleading page count.
+    pub fn drop_empty_pages(&self) -> ElectionSnapshot {
+        ElectionSnapshot {
+            schema_version: self.schema_version,
+            at: self.at,
+            round: self.round,
+            total_issuance: self.total_issuance,
+            desired_targets: self.desired_targets,
+            all_targets: self.all_targets.clone(),
+            voter_pages: self
+                .voter_pages
+                .iter()
+                .filter(|page| !page.is_empty())
+                .cloned()
+                .collect(),
+            checksum: None,
+        }
+    }
+
+    /// Return a copy of this snapshot with `excluded` removed from `all_targets` and
+    /// from every voter's target list.
+    ///
+    /// Voters left without targets are kept; they simply back nobody.
+    pub fn exclude_targets(&self, excluded: &BTreeSet<AccountId>) -> ElectionSnapshot {
+        ElectionSnapshot {
+            schema_version: self.schema_version,
+            at: self.at,
+            round: self.round,
+            total_issuance: self.total_issuance,
+            desired_targets: self.desired_targets,
+            all_targets: self
+                .all_targets
+                .iter()
+                .filter(|t| !excluded.contains(*t))
+                .cloned()
+                .collect(),
+            voter_pages: self
+                .voter_pages
+                .iter()
+                .map(|page| {
+                    page.iter()
+                        .map(|v| VoterSnapshot {
+                            who: v.who,
+                            weight: v.weight,
+                            targets: v
+                                .targets
+                                .iter()
+                                .filter(|t| !excluded.contains(*t))
+                                .cloned()
+                                .collect(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            checksum: None,
+        }
+    }
+
+    /// Sum of the weights of all voters nominating each target in `all_targets`
+    /// (targets nobody nominates map to `0`).
+    pub fn total_vote_weight_by_target(&self) -> BTreeMap<AccountId, u128> {
+        let mut totals: BTreeMap<AccountId, u128> =
+            self.all_targets.iter().map(|t| (*t, 0)).collect();
+        for voter in self.voter_pages.iter().flatten() {
+            for target in &voter.targets {
+                if let Some(total) = totals.get_mut(target) {
+                    *total = total.saturating_add(voter.weight as u128);
+                }
+            }
+        }
+        totals
+    }
+
+    /// Return a copy of this snapshot keeping only the `n` targets with the highest
+    /// `total_vote_weight_by_target`, removed from voter target lists as well.
+    ///
+    /// Ties keep the earlier target in `all_targets`.
+    pub fn trim_targets_to_top_n_by_nominating_weight(&self, n: usize) -> ElectionSnapshot {
+        let totals = self.total_vote_weight_by_target();
+        let mut ranked: Vec<&AccountId> = self.all_targets.iter().collect();
+        ranked.sort_by_key(|t| std::cmp::Reverse(totals.get(*t).copied().unwrap_or(0)));
+        let excluded: BTreeSet<AccountId> = ranked.into_iter().skip(n).copied().collect();
+        self.exclude_targets(&excluded)
+    }
+
+    /// Return a copy of this snapshot with all voter pages merged into a single page.
+    ///
+    /// Voters are deduplicated by `AccountId`, keeping the first occurrence in page order.
+    ///
+    /// NOTE: this is *not* the canonical representation. The on-chain miner consumes the
+    /// paged layout; only use the merged form for algorithms that cannot take paged input.
+    pub fn merge_voter_pages(&self) -> ElectionSnapshot {
+        let mut seen: BTreeSet<AccountId> = BTreeSet::new();
+        let mut merged: Vec<VoterSnapshot> = Vec::new();
+
+        for page in &self.voter_pages {
+            for v in page {
+                if seen.insert(v.who) {
+                    merged.push(v.clone());
+                }
+            }
+        }
+
+        ElectionSnapshot {
+            schema_version: self.schema_version,
+            at: self.at,
+            round: self.round,
+            total_issuance: self.total_issuance,
+            desired_targets: self.desired_targets,
+            all_targets: self.all_targets.clone(),
+            voter_pages: vec![merged],
+            checksum: None,
+        }
+    }
+}
+
+/// Whether an account is a target, a voter, both or neither in a snapshot.
+///
+/// Validators usually self-vote, so they show up as `Both`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountRole {
+    TargetOnly,
+    VoterOnly,
+    Both,
+    None,
 }
 
 /// Result of an offline election simplified for inspection.
 /// Support is in weight units, not raw on-chain balances.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OfflineWinner {
+    #[serde(with = "hex32")]
     pub validator: AccountId,
     pub support: VoteWeight,
     pub backers: Vec<OfflineBacker>,
 }
 
+impl OfflineWinner {
+    /// Share of `support` coming from the single largest backer; `0.0` without support.
+    ///
+    /// Above `0.5`, one nominator alone decides whether this validator gets elected.
+    pub fn concentration_ratio(&self) -> f64 {
+        if self.support == 0 {
+            return 0.0;
+        }
+        let max = self.backers.iter().map(|b| b.weight).max().unwrap_or(0);
+        max as f64 / self.support as f64
+    }
+
+    /// Herfindahl-Hirschman index of backer shares: `sum((weight / support)^2)`,
+    /// from `1 / backers` (even split) to `1.0` (single backer); `0.0` without support.
+    pub fn herfindahl_index(&self) -> f64 {
+        if self.support == 0 {
+            return 0.0;
+        }
+        self.backers
+            .iter()
+            .map(|b| {
+                let share = b.weight as f64 / self.support as f64;
+                share * share
+            })
+            .sum()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OfflineBacker {
+    #[serde(with = "hex32")]
     pub who: AccountId,
     pub weight: VoteWeight,
 }
 
+/// Final ratio assignment of one voter, for inspection.
+/// Ratios are `PerU16` values rendered as fractions in `[0, 1]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OfflineRatioAssignment {
+    #[serde(with = "hex32")]
+    pub voter: AccountId,
+    pub distribution: Vec<OfflineRatioShare>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OfflineRatioShare {
+    #[serde(with = "hex32")]
+    pub target: AccountId,
+    pub ratio: f64,
+}
+
+/// A snapshot target with the total weight of all voters that nominate it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetApproval {
+    #[serde(with = "hex32")]
+    pub target: AccountId,
+    pub approval_stake: u128,
+    pub elected: bool,
+}
+
+/// Renders `AccountId`s for display: SS58 with `ss58_prefix` if set, `0x` hex otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AccountFormatter {
+    pub ss58_prefix: Option<u16>,
+}
+
+impl AccountFormatter {
+    pub fn fmt(&self, id: &AccountId) -> String {
+        match self.ss58_prefix {
+            Some(prefix) => ss58_encode(id, prefix),
+            None => format!("0x{}", hex::encode(id)),
+        }
+    }
+}
+
+/// Process-wide formatter, set once from `--ss58-prefix` at startup.
+static ACCOUNT_FORMATTER: OnceLock<AccountFormatter> = OnceLock::new();
+
+/// Install the process-wide `AccountFormatter`. Only the first call takes effect.
+pub fn init_account_formatter(formatter: AccountFormatter) {
+    let _ = ACCOUNT_FORMATTER.set(formatter);
+}
+
+/// The process-wide `AccountFormatter` (hex if never initialized).
+pub fn account_formatter() -> &'static AccountFormatter {
+    ACCOUNT_FORMATTER.get_or_init(AccountFormatter::default)
+}
+
+/// Renders `Balance`s in token units with an SI-style prefix, e.g. `123.456789 DOT`,
+/// `1.250000 KDOT` or `3.5000 µDOT`; amounts below a micro-unit are shown in plancks
+/// (`42 pDOT`). The fraction is truncated to at most 6 digits.
+#[derive(Clone, Debug)]
+pub struct BalanceFormatter {
+    decimals: u8,
+    symbol: String,
+}
+
+impl BalanceFormatter {
+    /// Largest supported `decimals`, so that a mega-unit still fits in `Balance`.
+    pub const MAX_DECIMALS: u8 = 30;
+
+    pub fn new(decimals: u8, symbol: &str) -> Self {
+        assert!(
+            decimals <= Self::MAX_DECIMALS,
+            "decimals must be at most {}",
+            Self::MAX_DECIMALS
+        );
+        Self {
+            decimals,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    /// Format `b` plancks with the largest fitting metric prefix, down to micro.
+    /// Smaller amounts are printed as a plain `planck` count.
+    pub fn format(&self, b: Balance) -> String {
+        if b == 0 {
+            return format!("0 {}", self.symbol);
+        }
+
+        let unit: Balance = 10u128.pow(self.decimals as u32);
+        let scales = [
+            (unit * 1_000_000, "M"),
+            (unit * 1_000, "K"),
+            (unit, ""),
+            (unit / 1_000, "m"),
+            (unit / 1_000_000, "µ"),
+        ];
+        // Sub-unit prefixes only apply when `decimals` is large enough for them.
+        let min_divisor = match self.decimals {
+            0..=2 => unit,
+            3..=5 => unit / 1_000,
+            _ => unit / 1_000_000,
+        };
+
+        let Some((divisor, prefix)) = scales
+            .into_iter()
+            .find(|(d, _)| *d >= min_divisor && b >= *d)
+        else {
+            return format!("{} planck", b);
+        };
+
+        let digits = divisor.ilog10().min(6);
+        let int = b / divisor;
+        let frac = (b % divisor) / (divisor / 10u128.pow(digits));
+        if digits == 0 {
+            format!("{} {}{}", int, prefix, self.symbol)
+        } else {
+            format!(
+                "{}.{:0width$} {}{}",
+                int,
+                frac,
+                prefix,
+                self.symbol,
+                width = digits as usize
+            )
+        }
+    }
+}
+
+impl Default for BalanceFormatter {
+    /// Polkadot: 10 decimals, `DOT`.
+    fn default() -> Self {
+        Self::new(10, "DOT")
+    }
+}
+
+/// Process-wide formatter, set once from `--token-decimals` / `--token-symbol` at startup.
+static BALANCE_FORMATTER: OnceLock<BalanceFormatter> = OnceLock::new();
+
+/// Install the process-wide `BalanceFormatter`. Only the first call takes effect.
+pub fn init_balance_formatter(formatter: BalanceFormatter) {
+    let _ = BALANCE_FORMATTER.set(formatter);
+}
+
+/// The process-wide `BalanceFormatter` (Polkadot DOT if never initialized).
+pub fn balance_formatter() -> &'static BalanceFormatter {
+    BALANCE_FORMATTER.get_or_init(BalanceFormatter::default)
+}
+
+/// Largest SS58 address type (14 bits).
+pub const SS58_PREFIX_MAX: u16 = 0x3fff;
+
+/// SS58-encode a 32-byte account with the given address type:
+/// `base58(prefix ++ id ++ blake2_512("SS58PRE" ++ prefix ++ id)[..2])`.
+///
+/// Prefixes below 64 take one byte; larger ones use the two-byte form.
+fn ss58_encode(id: &AccountId, prefix: u16) -> String {
+    let mut data: Vec<u8> = match prefix {
+        0..=63 => vec![prefix as u8],
+        _ => {
+            let ident = prefix & SS58_PREFIX_MAX;
+            let first = (((ident & 0b0000_0000_1111_1100) as u8) >> 2) | 0b0100_0000;
+            let second = ((ident >> 8) as u8) | (((ident & 0b0000_0000_0000_0011) as u8) << 6);
+            vec![first, second]
+        }
+    };
+    data.extend_from_slice(id);
+
+    let mut preimage = b"SS58PRE".to_vec();
+    preimage.extend_from_slice(&data);
+    let checksum = sp_core::hashing::blake2_512(&preimage);
+    data.extend_from_slice(&checksum[..2]);
+
+    bs58::encode(data).into_string()
+}
+
+/// Returns `true` if no `AccountId` appears more than once in `ids`.
+pub fn all_unique(ids: &[AccountId]) -> bool {
+    let mut seen: BTreeSet<AccountId> = BTreeSet::new();
+    ids.iter().all(|id| seen.insert(*id))
+}
+
 /// Serialize an `ElectionSnapshot` to pretty JSON.
 pub fn snapshot_to_json(snapshot: &ElectionSnapshot) -> Result<String, serde_json::Error> {
-    serde_json::to_string_pretty(snapshot)
+    serde_json::to_string_pretty(&snapshot.with_checksum())
+}
+
+/// Current `ElectionSnapshot` JSON layout, written by `snapshot_to_json`.
+///
+/// - `1`: hashes and accounts as JSON integer arrays, no `schema_version` field.
+/// - `2`: hashes and accounts as `0x` hex strings.
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 2;
+
+/// Error loading an `ElectionSnapshot` from JSON.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Malformed JSON or an unsupported layout.
+    Json(serde_json::Error),
+    /// The embedded `checksum` does not match the snapshot content.
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Json(e) => write!(f, "{}", e),
+            SnapshotError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "snapshot checksum mismatch: file says {}, content hashes to {} \
+                 (use --skip-checksum for hand-edited snapshots)",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::Json(e)
+    }
+}
+
+/// Deserialize an `ElectionSnapshot` from JSON, upgrading older layouts via `migrate`,
+/// and verify its `checksum` if it has one.
+pub fn snapshot_from_json(data: &str) -> Result<ElectionSnapshot, SnapshotError> {
+    let snapshot = snapshot_from_json_unverified(data)?;
+    if let Some(expected) = &snapshot.checksum {
+        let actual = snapshot.compute_checksum();
+        if *expected != actual {
+            return Err(SnapshotError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Like `snapshot_from_json`, without checking the `checksum`.
+pub fn snapshot_from_json_unverified(data: &str) -> Result<ElectionSnapshot, serde_json::Error> {
+    migrate(data)
+}
+
+/// Just enough of a snapshot file to tell its layout version.
+#[derive(Deserialize)]
+struct LayoutProbe {
+    schema_version: Option<u64>,
+    at: Option<serde_json::Value>,
+}
+
+/// Upgrade a snapshot in any known JSON layout into the current `ElectionSnapshot`.
+///
+/// Files without `schema_version` predate the field and are detected from the type
+/// of `at` (integer array: v1, hex string: v2). Versions newer than
+/// `SNAPSHOT_SCHEMA_VERSION` are rejected rather than guessed at.
+///
+/// Each layout is deserialized straight from `data`: a `serde_json::Value` would
+/// turn a `total_issuance` above `u64::MAX` into a float and reject it.
+pub fn migrate(data: &str) -> Result<ElectionSnapshot, serde_json::Error> {
+    use serde::de::Error;
+
+    let probe: LayoutProbe = serde_json::from_str(data)?;
+    let version = match probe.schema_version {
+        Some(v) => v,
+        None if probe.at.as_ref().is_some_and(serde_json::Value::is_array) => 1,
+        None => 2,
+    };
+
+    match version {
+        1 => Ok(migrate_v1(serde_json::from_str(data)?)),
+        2 => {
+            let mut snapshot: ElectionSnapshot = serde_json::from_str(data)?;
+            snapshot.schema_version = SNAPSHOT_SCHEMA_VERSION;
+            Ok(snapshot)
+        }
+        v if v > SNAPSHOT_SCHEMA_VERSION as u64 => Err(Error::custom(format!(
+            "snapshot schema_version {} is newer than the latest supported version {}",
+            v, SNAPSHOT_SCHEMA_VERSION
+        ))),
+        v => Err(Error::custom(format!(
+            "unknown snapshot schema_version {}",
+            v
+        ))),
+    }
+}
+
+/// v1 `VoterSnapshot` layout, with accounts as `[u8; 32]` integer arrays.
+#[derive(Deserialize)]
+struct LegacyVoterSnapshot {
+    who: AccountId,
+    weight: VoteWeight,
+    targets: Vec<AccountId>,
 }
 
-/// Deserialize an `ElectionSnapshot` from JSON.
-pub fn snapshot_from_json(data: &str) -> Result<ElectionSnapshot, serde_json::Error> {
-    serde_json::from_str(data)
+/// v1 `ElectionSnapshot` layout, with hashes and accounts as integer arrays.
+#[derive(Deserialize)]
+struct LegacyElectionSnapshot {
+    at: Hash,
+    round: u32,
+    total_issuance: Balance,
+    desired_targets: u32,
+    all_targets: Vec<AccountId>,
+    voter_pages: Vec<Vec<LegacyVoterSnapshot>>,
+}
+
+/// Upgrade a v1 snapshot, written before hashes and accounts were serialized as
+/// hex strings.
+fn migrate_v1(legacy: LegacyElectionSnapshot) -> ElectionSnapshot {
+    ElectionSnapshot {
+        schema_version: SNAPSHOT_SCHEMA_VERSION,
+        at: legacy.at,
+        round: legacy.round,
+        total_issuance: legacy.total_issuance,
+        desired_targets: legacy.desired_targets,
+        all_targets: legacy.all_targets,
+        voter_pages: legacy
+            .voter_pages
+            .into_iter()
+            .map(|page| {
+                page.into_iter()
+                    .map(|v| VoterSnapshot {
+                        who: v.who,
+                        weight: v.weight,
+                        targets: v.targets,
+                    })
+                    .collect()
+            })
+            .collect(),
+        checksum: None,
+    }
 }
 
 /// Mirror how `SaturatingCurrencyToVote` maps `Balance` (`u128`) -> `VoteWeight` (`u64`):
@@ -84,3 +741,214 @@ pub fn balance_to_vote_weight(b: Balance) -> VoteWeight {
         b as VoteWeight
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn account() -> impl Strategy<Value = AccountId> {
+        any::<[u8; 32]>()
+    }
+
+    fn voter() -> impl Strategy<Value = VoterSnapshot> {
+        (account(), any::<VoteWeight>(), vec(account(), 0..4)).prop_map(|(who, weight, targets)| {
+            VoterSnapshot {
+                who,
+                weight,
+                targets,
+            }
+        })
+    }
+
+    /// Small snapshots in the current schema, checksummed as `snapshot_to_json` writes them.
+    fn snapshot() -> impl Strategy<Value = ElectionSnapshot> {
+        (
+            any::<Hash>(),
+            any::<u32>(),
+            any::<Balance>(),
+            any::<u32>(),
+            vec(account(), 0..8),
+            vec(vec(voter(), 0..6), 0..4),
+        )
+            .prop_map(
+                |(at, round, total_issuance, desired_targets, all_targets, voter_pages)| {
+                    ElectionSnapshot {
+                        schema_version: SNAPSHOT_SCHEMA_VERSION,
+                        at,
+                        round,
+                        total_issuance,
+                        desired_targets,
+                        all_targets,
+                        voter_pages,
+                        checksum: None,
+                    }
+                    .with_checksum()
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn snapshot_json_round_trip(s in snapshot()) {
+            let json = snapshot_to_json(&s).unwrap();
+            prop_assert_eq!(snapshot_from_json(&json).unwrap(), s);
+        }
+    }
+
+    fn v(who: u8, weight: VoteWeight, targets: &[u8]) -> VoterSnapshot {
+        VoterSnapshot {
+            who: [who; 32],
+            weight,
+            targets: targets.iter().map(|t| [*t; 32]).collect(),
+        }
+    }
+
+    fn snap(targets: &[u8], voter_pages: Vec<Vec<VoterSnapshot>>) -> ElectionSnapshot {
+        ElectionSnapshot {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            at: [9; 32],
+            round: 1,
+            total_issuance: 1_000,
+            desired_targets: 2,
+            all_targets: targets.iter().map(|t| [*t; 32]).collect(),
+            voter_pages,
+            checksum: None,
+        }
+    }
+
+    #[test]
+    fn merge_voter_pages_keeps_first_occurrence() {
+        let s = snap(
+            &[10, 11],
+            vec![
+                vec![v(1, 5, &[10]), v(2, 6, &[11])],
+                vec![v(1, 7, &[11]), v(3, 8, &[10])],
+            ],
+        );
+        let merged = s.merge_voter_pages();
+        assert_eq!(
+            merged.voter_pages,
+            vec![vec![v(1, 5, &[10]), v(2, 6, &[11]), v(3, 8, &[10])]]
+        );
+        assert_eq!(merged.all_targets, s.all_targets);
+    }
+
+    #[test]
+    fn canonicalize_ignores_ordering_but_keeps_pages() {
+        let a = snap(
+            &[11, 10],
+            vec![
+                vec![v(2, 6, &[11, 10]), v(1, 5, &[10])],
+                vec![v(3, 8, &[10])],
+            ],
+        );
+        let b = snap(
+            &[10, 11],
+            vec![
+                vec![v(1, 5, &[10]), v(2, 6, &[10, 11])],
+                vec![v(3, 8, &[10])],
+            ],
+        );
+        assert_eq!(a.canonicalize(), b.canonicalize());
+        assert_eq!(a.canonicalize(), b);
+
+        let repaged = snap(
+            &[10, 11],
+            vec![
+                vec![v(1, 5, &[10])],
+                vec![v(2, 6, &[10, 11]), v(3, 8, &[10])],
+            ],
+        );
+        assert_ne!(repaged.canonicalize(), b.canonicalize());
+    }
+
+    #[test]
+    fn exclude_targets_strips_targets_and_keeps_voters() {
+        let s = snap(
+            &[10, 11, 12],
+            vec![vec![v(1, 5, &[10, 11]), v(2, 6, &[11])]],
+        );
+        let out = s.exclude_targets(&BTreeSet::from([[11u8; 32]]));
+        assert_eq!(out.all_targets, vec![[10u8; 32], [12u8; 32]]);
+        assert_eq!(out.voter_pages, vec![vec![v(1, 5, &[10]), v(2, 6, &[])]]);
+    }
+
+    #[test]
+    fn trim_targets_keeps_heaviest_and_earlier_on_ties() {
+        // Nominating weight: 10 -> 5, 11 -> 11, 12 -> 6, 13 -> 6.
+        let s = snap(
+            &[10, 11, 12, 13],
+            vec![vec![v(1, 5, &[10, 11]), v(2, 6, &[11, 12, 13])]],
+        );
+        let out = s.trim_targets_to_top_n_by_nominating_weight(2);
+        assert_eq!(out.all_targets, vec![[11u8; 32], [12u8; 32]]);
+        assert_eq!(
+            out.voter_pages,
+            vec![vec![v(1, 5, &[11]), v(2, 6, &[11, 12])]]
+        );
+    }
+
+    #[test]
+    fn migrate_upgrades_v1_integer_arrays() {
+        let account = |b: u8| format!("[{}]", vec![b.to_string(); 32].join(","));
+        let v1 = format!(
+            r#"{{"at":{},"round":1,"total_issuance":1000,"desired_targets":2,
+               "all_targets":[{}],"voter_pages":[[{{"who":{},"weight":5,"targets":[{}]}}]]}}"#,
+            account(9),
+            account(10),
+            account(1),
+            account(10),
+        );
+        let migrated = migrate(&v1).unwrap();
+        assert_eq!(migrated, snap(&[10], vec![vec![v(1, 5, &[10])]]));
+
+        let newer = format!(r#"{{"schema_version":{}}}"#, SNAPSHOT_SCHEMA_VERSION + 1);
+        assert!(migrate(&newer).is_err());
+    }
+
+    #[test]
+    fn ss58_encode_matches_known_addresses() {
+        // Well-known `//Alice` sr25519 public key.
+        let alice: AccountId =
+            hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            ss58_encode(&alice, 0),
+            "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5"
+        );
+        assert_eq!(
+            ss58_encode(&alice, 42),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+    }
+
+    #[test]
+    fn balance_formatter_zero_and_sub_micro() {
+        let f = BalanceFormatter::default();
+        assert_eq!(f.format(0), "0 DOT");
+        assert_eq!(f.format(1), "1 planck");
+        // 1 µDOT is 10_000 plancks at 10 decimals.
+        assert_eq!(f.format(9_999), "9999 planck");
+    }
+
+    #[test]
+    fn balance_formatter_prefix_boundaries() {
+        let f = BalanceFormatter::default();
+        assert_eq!(f.format(10_000), "1.0000 µDOT");
+        assert_eq!(f.format(10_000_000_000 - 1), "999.999999 mDOT");
+        assert_eq!(f.format(10_000_000_000), "1.000000 DOT");
+        assert_eq!(f.format(10_000_000_000_000), "1.000000 KDOT");
+    }
+
+    #[test]
+    fn balance_formatter_max_balance() {
+        assert_eq!(
+            BalanceFormatter::default().format(u128::MAX),
+            "34028236692093846346337.460743 MDOT"
+        );
+    }
+}
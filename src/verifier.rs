@@ -0,0 +1,90 @@
+// src/verifier.rs
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use subxt::config::substrate::AccountId32;
+use subxt::utils::H256;
+use subxt::{OnlineClient, config::PolkadotConfig};
+
+use crate::asset_hub;
+use crate::asset_hub::api::runtime_types::pallet_election_provider_multi_block::verifier::impls::ValidSolution;
+use crate::types::{AccountId, Balance, Hash};
+
+/// Convert runtime `AccountId32` into local `[u8; 32]` alias.
+fn account32_to_local(acc: AccountId32) -> AccountId {
+    *acc.as_ref()
+}
+
+/// Winner of the queued (verified) solution, with its support summed over all pages.
+#[derive(Clone, Debug)]
+pub struct QueuedWinner {
+    pub validator: AccountId,
+    pub total: Balance,
+    pub backers: usize,
+}
+
+/// Fetch the winners of the valid queued solution of `round` from
+/// `pallet-election-provider-multi-block` verifier storage.
+///
+/// `QueuedValidVariant(round)` selects `QueuedSolutionX` or `QueuedSolutionY`, whose
+/// pages `[0..max_pages)` hold per-page supports. A winner can appear on several pages,
+/// so totals and backer counts are summed.
+///
+/// Returns `None` if no solution is queued for `round` at `at`.
+/// Winners are ordered by descending total support.
+pub async fn fetch_queued_winners(
+    client: &OnlineClient<PolkadotConfig>,
+    at: Hash,
+    round: u32,
+    max_pages: u32,
+) -> Result<Option<Vec<QueuedWinner>>> {
+    let storage = client.storage().at(H256::from(at));
+    let verifier = asset_hub::api::storage().multi_block_election_verifier();
+
+    let variant = storage
+        .fetch_or_default(&verifier.queued_valid_variant(round))
+        .await?;
+
+    let mut by_validator: BTreeMap<AccountId, QueuedWinner> = BTreeMap::new();
+    let mut found_any_page = false;
+
+    for page in 0..max_pages {
+        let supports = match variant {
+            ValidSolution::X => {
+                storage
+                    .fetch(&verifier.queued_solution_x(round, page))
+                    .await?
+            }
+            ValidSolution::Y => {
+                storage
+                    .fetch(&verifier.queued_solution_y(round, page))
+                    .await?
+            }
+        };
+
+        let Some(supports) = supports else {
+            continue;
+        };
+        found_any_page = true;
+
+        for (who, support) in supports.0.0 {
+            let validator = account32_to_local(who);
+            let entry = by_validator.entry(validator).or_insert(QueuedWinner {
+                validator,
+                total: 0,
+                backers: 0,
+            });
+            entry.total = entry.total.saturating_add(support.total);
+            entry.backers += support.voters.0.len();
+        }
+    }
+
+    if !found_any_page {
+        return Ok(None);
+    }
+
+    let mut winners: Vec<QueuedWinner> = by_validator.into_values().collect();
+    winners.sort_by_key(|w| std::cmp::Reverse(w.total));
+    Ok(Some(winners))
+}